@@ -1,62 +1,178 @@
-use arcsys::ggst::pac::GGSTPac;
+use std::collections::BTreeMap;
 use eframe::egui::{self, ComboBox};
 use poll_promise::Promise;
 use self::boxes::BoxesWindow;
-use bbscript::{command_db::{GameDB}, error::BBScriptError, run_parser};
+use self::open::{ColFile, LocalFile, PickedFile, Slot};
+use self::game::Game;
+use self::roster::{Character, RosterOrigin};
+use self::source::AssetSource;
+use self::error::LoadError;
+use self::framedata::TableFormat;
+use self::verify::Report;
+use bbscript::{command_db::{GameDB}, run_parser};
 
-mod open;
+pub(crate) mod open;
 mod boxes;
+mod error;
+pub(crate) mod framedata;
+pub mod game;
+mod history;
+mod legend;
+mod overlay;
+pub(crate) mod json;
+mod roster;
+mod save;
+mod source;
+pub(crate) mod states;
+pub mod verify;
+mod view;
+
+const ASSET_SOURCE_KEY: &str = "asset_source";
+const GAME_KEY: &str = "game";
+const CUSTOM_CHARACTERS_KEY: &str = "custom_characters";
+const LEGEND_KEY: &str = "legend";
+const OVERLAYS_KEY: &str = "overlays";
+
+/// A reload that would throw away the edited boxes, held until the user decides what to do.
+enum PendingReload {
+    Character(String),
+    Game(Game),
+    AssetSource,
+    Collision(LocalFile),
+}
+
+impl PendingReload {
+    fn description(&self) -> String {
+        match self {
+            PendingReload::Character(code) => format!("switching to {}", code),
+            PendingReload::Game(game) => format!("switching to {}", game.name()),
+            PendingReload::AssetSource => "reloading from the new asset source".to_string(),
+            PendingReload::Collision(file) => format!("opening {}", file.name),
+        }
+    }
+}
 
 #[derive(Default)]
 pub struct MyApp {
-    col_promise: Option<Promise<Result<GGSTPac, String>>>,
-    char_promise: Option<Promise<Vec<u8>>>,
-    ef_promise: Option<Promise<Vec<u8>>>,
-    ron_promise: Option<Promise<Result<GameDB, BBScriptError>>>,
-    image_promise: Option<Promise<Vec<u8>>>,
+    col_promise: Option<Promise<Result<ColFile, LoadError>>>,
+    char_promise: Option<Promise<Result<Vec<u8>, LoadError>>>,
+    ef_promise: Option<Promise<Result<Vec<u8>, LoadError>>>,
+    ron_promise: Option<Promise<Result<GameDB, LoadError>>>,
+    image_promise: Option<Promise<Result<Vec<u8>, LoadError>>>,
+    /// Renders of the playing state's other sprites, by sprite name.
+    image_prefetch: BTreeMap<String, Promise<Result<Vec<u8>, LoadError>>>,
+    char_script_error: Option<LoadError>,
+    ef_script_error: Option<LoadError>,
+    image_error: Option<LoadError>,
     boxes_window: BoxesWindow,
     loaded: bool,
     selected: String,
-    ggst_file_list: Vec<String>,
+    /// Whether `selected` was picked automatically rather than by the user.
+    default_selected: bool,
+    game: Game,
+    file_list: Vec<Character>,
+    roster_promise: Option<Promise<Result<(Vec<Character>, RosterOrigin), LoadError>>>,
+    roster_origin: Option<RosterOrigin>,
+    custom_characters: BTreeMap<Game, Vec<String>>,
+    new_character: String,
     file_changed: bool,
+    save_status: Option<Result<String, String>>,
+    pending_open: Option<(Slot, Promise<PickedFile>)>,
+    open_status: Option<String>,
+    /// Result of the last round-trip check, shown in a window until it is closed.
+    verify_report: Option<Result<Report, String>>,
+    local_col_name: Option<String>,
+    asset_source: AssetSource,
+    source_settings: AssetSource,
+    show_settings: bool,
+    pending_reload: Option<PendingReload>,
 }
 
 impl eframe::App for MyApp {
+    fn save(&mut self, storage: &mut dyn eframe::Storage) {
+        eframe::set_value(storage, ASSET_SOURCE_KEY, &self.asset_source);
+        eframe::set_value(storage, GAME_KEY, &self.game);
+        eframe::set_value(storage, CUSTOM_CHARACTERS_KEY, &self.custom_characters);
+        eframe::set_value(storage, LEGEND_KEY, &self.boxes_window.legend);
+        eframe::set_value(storage, OVERLAYS_KEY, &self.boxes_window.overlays);
+    }
+
     fn update(&mut self, ctx: &egui::Context, _frame: &mut eframe::Frame) {
-        if self.selected == "".to_string() {
-            self.selected = "SOL".to_string();
-        }
-        if self.ggst_file_list.len() == 0 {
-            self.ggst_file_list.push("SOL".to_string());
-            self.ggst_file_list.push("KYK".to_string());
-            self.ggst_file_list.push("MAY".to_string());
-            self.ggst_file_list.push("AXL".to_string());
-            self.ggst_file_list.push("CHP".to_string());
-            self.ggst_file_list.push("POT".to_string());
-            self.ggst_file_list.push("FAU".to_string());
-            self.ggst_file_list.push("MLL".to_string());
-            self.ggst_file_list.push("ZAT".to_string());
-            self.ggst_file_list.push("RAM".to_string());
-            self.ggst_file_list.push("LEO".to_string());
-            self.ggst_file_list.push("NAG".to_string());
-            self.ggst_file_list.push("GIO".to_string());
-            self.ggst_file_list.push("ANJ".to_string());
-            self.ggst_file_list.push("INO".to_string());
-            self.ggst_file_list.push("GLD".to_string());
-            self.ggst_file_list.push("JKO".to_string());
-            self.ggst_file_list.push("COS".to_string());
-            self.ggst_file_list.push("BKN".to_string());
-            self.ggst_file_list.push("TST".to_string());
+        self.boxes_window.game = self.game;
+        let roster_promise = self.roster_promise.get_or_insert_with(|| {
+            let ctx = ctx.clone();
+            let (sender, promise) = Promise::new();
+            roster::load(&self.asset_source, self.game, move |roster| {
+                sender.send(roster);
+                ctx.request_repaint();
+            });
+            promise
+        });
+        if self.file_list.is_empty() {
+            // Until the roster has been discovered, or if there isn't one, fall back to the built-in list.
+            let (discovered, origin) = match roster_promise.ready() {
+                Some(Ok((characters, origin))) if !characters.is_empty() => (characters.clone(), *origin),
+                _ => (roster::built_in(self.game), RosterOrigin::BuiltIn),
+            };
+            let custom = self.custom_characters.get(&self.game).map(Vec::as_slice).unwrap_or_default();
+            self.file_list = roster::merge(self.game, discovered, custom);
+            self.roster_origin = Some(origin);
+        }
+        // Start on the first character of the roster. If that was picked before the asset source's
+        // roster arrived and isn't part of it, move on to one that is, unless it is being edited.
+        let selected_missing = !self.file_list.iter().any(|character| character.code == self.selected);
+        let replace_default = self.default_selected
+            && selected_missing
+            && self.local_col_name.is_none()
+            && self.boxes_window.unsaved.is_empty();
+        if self.selected.is_empty() || replace_default {
+            if let Some(first) = self.file_list.first() {
+                if !self.selected.is_empty() {
+                    self.col_promise = None;
+                    self.char_promise = None;
+                    self.ef_promise = None;
+                }
+                self.selected = first.code.clone();
+                self.default_selected = true;
+            }
+        }
+        if self.roster_origin == Some(RosterOrigin::BuiltIn)
+            && matches!(roster_promise.ready(), Some(Ok((characters, _))) if !characters.is_empty())
+        {
+            // The discovered roster arrived after the fallback was shown, so rebuild the list.
+            self.file_list = Default::default();
+            self.roster_origin = None;
+        }
+        if let Some((slot, promise)) = self.pending_open.take() {
+            match promise.try_take() {
+                Ok(Some(Ok(file))) => self.open_local(slot, file),
+                Ok(Some(Err(e))) => self.open_status = Some(format!("Failed to open {}! {}", slot.description().to_lowercase(), e)),
+                Ok(None) => (),
+                Err(promise) => self.pending_open = Some((slot, promise)),
+            }
+        }
+        let dropped_files = ctx.input().raw.dropped_files.clone();
+        for dropped in &dropped_files {
+            match open::read_dropped(dropped) {
+                Ok(file) => match Slot::classify(&file.name, &file.bytes) {
+                    Some(slot) => {
+                        self.open_status = None;
+                        self.open_local(slot, file);
+                    }
+                    None => self.open_status = Some(format!("Don't know how to open {}", file.name)),
+                },
+                Err(e) => self.open_status = Some(e),
+            }
         }
         let col_promise = self.col_promise.get_or_insert_with(|| {
             // Begin download.
-            // We download the image using `ehttp`, a library that works both in WASM and on native.
+            // Remote sources are downloaded with `ehttp`, a library that works both in WASM and on native.
             // We use the `poll-promise` library to communicate with the UI thread.
             let ctx = ctx.clone();
             let (sender, promise) = Promise::new();
-            let request = ehttp::Request::get(format!("https://wistfulhopes.neocities.org/pacs/COL_{}.pac", self.selected));
-            ehttp::fetch(request, move |response| {
-                let pac = response.and_then(parse_col);
+            let game = self.game;
+            self.asset_source.fetch(&self.game.asset_path(&format!("pacs/COL_{}.pac", self.selected)), move |response| {
+                let pac = response.and_then(|bytes| open::open_file(bytes, game).map_err(LoadError::Pac));
                 sender.send(pac); // send the results back to the UI thread.
                 ctx.request_repaint(); // wake up UI thread
             });
@@ -66,45 +182,40 @@ impl eframe::App for MyApp {
         
         let char_promise = self.char_promise.get_or_insert_with(|| {
             // Begin download.
-            // We download the image using `ehttp`, a library that works both in WASM and on native.
+            // Remote sources are downloaded with `ehttp`, a library that works both in WASM and on native.
             // We use the `poll-promise` library to communicate with the UI thread.
             let ctx = ctx.clone();
             let (sender, promise) = Promise::new();
-            let request = ehttp::Request::get(format!("https://wistfulhopes.neocities.org/scripts/BBS_{}.bbscript", self.selected));
-            ehttp::fetch(request, move |response| {
-                let charscript = response_to_bytes(response.unwrap());
+            self.asset_source.fetch(&self.game.asset_path(&format!("scripts/BBS_{}.bbscript", self.selected)), move |response| {
+                let charscript = response;
                 sender.send(charscript); // send the results back to the UI thread.
                 ctx.request_repaint(); // wake up UI thread
             });
-            self.loaded = false;
             promise
         });
         
         let ef_promise = self.ef_promise.get_or_insert_with(|| {
             // Begin download.
-            // We download the image using `ehttp`, a library that works both in WASM and on native.
+            // Remote sources are downloaded with `ehttp`, a library that works both in WASM and on native.
             // We use the `poll-promise` library to communicate with the UI thread.
             let ctx = ctx.clone();
             let (sender, promise) = Promise::new();
-            let request = ehttp::Request::get(format!("https://wistfulhopes.neocities.org/scripts/BBS_{}EF.bbscript", self.selected));
-            ehttp::fetch(request, move |response| {
-                let efscript = response_to_bytes(response.unwrap());
+            self.asset_source.fetch(&self.game.asset_path(&format!("scripts/BBS_{}EF.bbscript", self.selected)), move |response| {
+                let efscript = response;
                 sender.send(efscript); // send the results back to the UI thread.
                 ctx.request_repaint(); // wake up UI thread
             });
-            self.loaded = false;
             promise
         });
         
         let image_promise = self.image_promise.get_or_insert_with(|| {
             // Begin download.
-            // We download the image using `ehttp`, a library that works both in WASM and on native.
+            // Remote sources are downloaded with `ehttp`, a library that works both in WASM and on native.
             // We use the `poll-promise` library to communicate with the UI thread.
             let ctx = ctx.clone();
             let (sender, promise) = Promise::new();
-            let request = ehttp::Request::get(format!("https://wistfulhopes.neocities.org/images/{}/{}.png", self.selected, self.boxes_window.selected));
-            ehttp::fetch(request, move |response| {
-                let image = response_to_bytes(response.unwrap());
+            self.asset_source.fetch(&self.game.asset_path(&format!("images/{}/{}.png", self.selected, self.boxes_window.selected)), move |response| {
+                let image = response;
                 sender.send(image); // send the results back to the UI thread.
                 ctx.request_repaint(); // wake up UI thread
             });
@@ -114,160 +225,659 @@ impl eframe::App for MyApp {
         
         let ron_promise = self.ron_promise.get_or_insert_with(|| {
             // Begin download.
-            // We download the image using `ehttp`, a library that works both in WASM and on native.
+            // Remote sources are downloaded with `ehttp`, a library that works both in WASM and on native.
             // We use the `poll-promise` library to communicate with the UI thread.
             let ctx = ctx.clone();
             let (sender, promise) = Promise::new();
-            let request = ehttp::Request::get(format!("https://wistfulhopes.neocities.org/rons/ggst.ron"));
-            ehttp::fetch(request, move |response| {
-                let ron = parse_ron(response.unwrap());
+            self.asset_source.fetch(&self.game.asset_path(self.game.command_db()), move |response| {
+                let ron = response.and_then(parse_ron);
                 sender.send(ron); // send the results back to the UI thread.
                 ctx.request_repaint(); // wake up UI thread
             });
-            self.loaded = false;
             promise
         });
+        // Only a new collision file starts the editor over. Scripts and the command DB are
+        // fetched again without touching the boxes, see `retry`.
         if !self.loaded {
             self.boxes_window.reset();
+            self.image_prefetch = Default::default();
         }
         self.file_changed = false;
 
-        egui::CentralPanel::default().show(ctx, |ui| {
+        let mut save_requested = false;
+        let mut export_requested = None;
+        let mut export_json_requested = None;
+        let mut verify_requested = false;
+        let mut open_requested = None;
+        egui::TopBottomPanel::top("menu_bar").show(ctx, |ui| {
+            egui::menu::bar(ui, |ui| {
+                ui.menu_button("File", |ui| {
+                    for slot in [Slot::Collision, Slot::CharScript, Slot::EffectScript, Slot::GameDb, Slot::Image] {
+                        if ui.button(format!("Open {}…", slot.description().to_lowercase())).clicked() {
+                            open_requested = Some(slot);
+                            ui.close_menu();
+                        }
+                    }
+                    if ui.add_enabled(self.loaded, egui::Button::new("Import JSON…")).clicked() {
+                        open_requested = Some(Slot::Json);
+                        ui.close_menu();
+                    }
+                    ui.separator();
+                    if ui.add_enabled(self.loaded, egui::Button::new("Save PAC…")).clicked() {
+                        save_requested = true;
+                        ui.close_menu();
+                    }
+                    if ui.add_enabled(self.loaded, egui::Button::new("Export PAC as JSON…")).clicked() {
+                        export_json_requested = Some(None);
+                        ui.close_menu();
+                    }
+                    let selected_sprite = self.boxes_window.selected_sprite().map(str::to_string);
+                    if ui.add_enabled(self.loaded && selected_sprite.is_some(), egui::Button::new("Export sprite as JSON…")).clicked() {
+                        export_json_requested = Some(selected_sprite);
+                        ui.close_menu();
+                    }
+                    if ui.add_enabled(self.loaded, egui::Button::new("Check round trip")).on_hover_text("Write the PAC back without edits and compare it to the original").clicked() {
+                        verify_requested = true;
+                        ui.close_menu();
+                    }
+                    let has_states = self.loaded && self.boxes_window.has_states();
+                    ui.add_enabled_ui(has_states, |ui| {
+                        ui.menu_button("Export frame data", |ui| {
+                            for (format, label) in [(TableFormat::Csv, "CSV…"), (TableFormat::Json, "JSON…")] {
+                                if ui.button(label).clicked() {
+                                    export_requested = Some(format);
+                                    ui.close_menu();
+                                }
+                            }
+                        });
+                    });
+                });
+                ui.menu_button("Edit", |ui| {
+                    self.boxes_window.edit_menu(ui);
+                });
+                if ui.button("Settings").clicked() {
+                    self.source_settings = self.asset_source.clone();
+                    self.show_settings = true;
+                }
+            });
+        });
+
+        if !ctx.input().raw.hovered_files.is_empty() {
+            egui::Area::new("drop_hint")
+            .anchor(egui::Align2::CENTER_CENTER, egui::Vec2::ZERO)
+            .show(ctx, |ui| {
+                ui.heading("Drop a .pac, .bbscript, .ron or .png file to load it");
+            });
+        }
+
+        let mut reload_requested = false;
+        egui::Window::new("Asset source")
+        .open(&mut self.show_settings)
+        .show(ctx, |ui| {
+            reload_requested = self.source_settings.ui(ui);
+        });
+
+        let mut reload = None;
+        let retry = egui::CentralPanel::default().show(ctx, |ui| {
             ui.horizontal(|ui| {
-                ComboBox::from_label("Strive")
-                .selected_text(format!("{:?}", self.selected))
-                .width(150.0)
+                ComboBox::from_id_source("game")
+                .selected_text(self.game.name())
+                .show_ui(ui, |ui| {
+                    for game in Game::ALL {
+                        if ui.selectable_label(self.game == game, game.name())
+                        .clicked()
+                        && self.game != game
+                        {
+                            reload = Some(PendingReload::Game(game));
+                        }
+                    }
+                });
+                let selected_label = self.file_list.iter()
+                    .find(|character| character.code == self.selected)
+                    .map_or_else(|| self.selected.clone(), Character::label);
+                ComboBox::from_label("Character")
+                .selected_text(selected_label)
+                .width(200.0)
                 .show_ui(ui, |ui| {
-                    for name in &self.ggst_file_list {
-                        if ui.selectable_label(true, name)
+                    for character in &self.file_list {
+                        if ui.selectable_label(character.code == self.selected, character.label())
                         .clicked()
+                        && character.code != self.selected
                         {
-                            self.selected = name.clone();
-                            self.loaded = false;
-                            self.file_changed = true;
+                            reload = Some(PendingReload::Character(character.code.clone()));
                         };
                     }
                 });
+                if let Some(origin) = self.roster_origin {
+                    ui.weak(format!("Roster {}", origin.description()));
+                }
+                ui.add(egui::TextEdit::singleline(&mut self.new_character).hint_text("Custom code").desired_width(80.0));
+                let valid_code = roster::is_valid_code(self.new_character.trim());
+                if ui.add_enabled(valid_code, egui::Button::new("Add character"))
+                .on_disabled_hover_text("Codes may only contain letters, digits and underscores")
+                .clicked()
+                {
+                    let code = self.new_character.trim().to_string();
+                    let custom = self.custom_characters.entry(self.game).or_default();
+                    if !custom.contains(&code) {
+                        custom.push(code.clone());
+                    }
+                    self.new_character = Default::default();
+                    self.file_list = Default::default();
+                    if code != self.selected {
+                        reload = Some(PendingReload::Character(code));
+                    }
+                }
+                let selected_is_custom = self.file_list.iter().any(|character| character.custom && character.code == self.selected);
+                if selected_is_custom && ui.button("Remove character").clicked() {
+                    if let Some(custom) = self.custom_characters.get_mut(&self.game) {
+                        custom.retain(|code| code != &self.selected);
+                    }
+                    self.file_list = Default::default();
+                }
                 let mut visuals = ui.ctx().style().visuals.clone();
                 visuals.light_dark_radio_buttons(ui);
                 ui.ctx().set_visuals(visuals);
+                if !self.boxes_window.unsaved.is_empty() {
+                    ui.label(format!("{} edited sprite(s)", self.boxes_window.unsaved.len()));
+                }
+                if let Some(e) = &self.open_status {
+                    ui.colored_label(egui::Color32::RED, e);
+                }
+                match &self.save_status {
+                    Some(Ok(message)) => {
+                        ui.label(message);
+                    }
+                    Some(Err(e)) => {
+                        ui.colored_label(egui::Color32::RED, format!("Failed to save! {}", e));
+                    }
+                    None => (),
+                }
             });
+            let mut retry = None;
             match ron_promise.ready() {
                 None => {
                     ui.label("Loading game functions...");
-                    ()
                 }
                 Some(Err(e)) => {
-                    ui.label(format!("Failed to load game functions! {}", e));
-                    ()
+                    if load_error(ui, "game functions", e) {
+                        retry = Some(Slot::GameDb);
+                    }
                 }
                 Some(Ok(ron)) => {
                     ui.label("Game functions loaded!");
                     match char_promise.ready() {
                         None => {
                             ui.label("Loading character script...");
-                            ()
                         }
-                        Some(bytes) => {
+                        Some(Err(e)) => {
+                            if load_error(ui, "character script", e) {
+                                retry = Some(Slot::CharScript);
+                            }
+                        }
+                        Some(Ok(bytes)) => {
                             if self.boxes_window.char_script == "" {
                                 self.boxes_window.char_script = match run_parser(ron,bytes, Some(0 as usize), Some(0 as usize), false) {
-                                    Ok(script) => {script},
-                                    Err(_) => "Error".to_string()
+                                    Ok(script) => {
+                                        self.char_script_error = None;
+                                        script
+                                    },
+                                    Err(e) => {
+                                        self.char_script_error = Some(LoadError::Script(e.to_string()));
+                                        "Error".to_string()
+                                    }
                                 };
                                 self.boxes_window.collect_states();
                             }
-                            if self.boxes_window.char_script == "Error".to_string() {
-                                ui.label(format!("Failed to load character script!!"));
+                            if let Some(e) = &self.char_script_error {
+                                if load_error(ui, "character script", e) {
+                                    retry = Some(Slot::CharScript);
+                                }
                             }
-                            ()
                         }
                     };
 
                     match ef_promise.ready() {
                         None => {
                             ui.label("Loading effect script...");
-                            ()
                         }
-                        Some(bytes) => {
+                        Some(Err(e)) => {
+                            if load_error(ui, "effect script", e) {
+                                retry = Some(Slot::EffectScript);
+                            }
+                        }
+                        Some(Ok(bytes)) => {
                             if self.boxes_window.ef_script == "" {
                                 self.boxes_window.ef_script = match run_parser(ron,bytes, Some(0 as usize), Some(0 as usize), false) {
-                                    Ok(script) => script,
-                                    Err(_) => "Error".to_string()
+                                    Ok(script) => {
+                                        self.ef_script_error = None;
+                                        script
+                                    },
+                                    Err(e) => {
+                                        self.ef_script_error = Some(LoadError::Script(e.to_string()));
+                                        "Error".to_string()
+                                    }
                                 };
                                 self.boxes_window.collect_ef_states();
                             };
-                            if self.boxes_window.ef_script == "Error".to_string() {
-                                ui.label("Failed to load effect script!!");
+                            if let Some(e) = &self.ef_script_error {
+                                if load_error(ui, "effect script", e) {
+                                    retry = Some(Slot::EffectScript);
+                                }
                             }
-                            ()
                         }
                     };
 
+                    // Not every sprite has an image, so only mention a failed image once one is selected,
+                    // and not once a cached render of the sprite is shown instead.
                     match image_promise.ready() {
-                        None => {
-                            ()
+                        Some(Err(e)) if self.boxes_window.selected != "" && self.boxes_window.texture.is_none() => {
+                            if load_error(ui, "sprite image", e) {
+                                retry = Some(Slot::Image);
+                            }
                         }
-                        Some(bytes) => {
-                            if self.boxes_window.image.is_none() {
+                        Some(Ok(bytes)) => {
+                            if self.boxes_window.image.is_none() && self.image_error.is_none() {
                                 match self.boxes_window.bytes_to_image(bytes) {
                                     Ok(image) => {
                                         self.boxes_window.image = Some(image);
                                     }
-                                    Err(_) => (),
+                                    Err(e) => self.image_error = Some(LoadError::Decode(e.to_string())),
                                 }    
                             }
+                            if let Some(e) = &self.image_error {
+                                if load_error(ui, "sprite image", e) {
+                                    retry = Some(Slot::Image);
+                                }
+                            }
                         }
+                        _ => (),
                     }
                 }
             };
             match col_promise.ready() {
                 None => {
                     ui.label("Loading collision data...");
-                    ()
                 }
                 Some(Err(e)) => {
-                    ui.label(format!("Failed to read pac! Error: {}", e));
-                    ()
+                    if load_error(ui, "collision data", e) {
+                        retry = Some(Slot::Collision);
+                    }
                 },
-                Some(Ok(pac)) => {
+                Some(Ok(col)) => {
                     if !self.loaded {
-                        self.boxes_window.open_file(&pac);
+                        self.boxes_window.open_file(&col.pac, &col.offsets);
                     }
                     self.loaded = true;
                     self.boxes_window.ui(ui);
                 }
             }
+            retry
+        }).inner;
+        if let Some(slot) = retry {
+            self.retry(slot);
+        }
+        if reload_requested {
+            self.show_settings = false;
+            reload = Some(PendingReload::AssetSource);
+        }
+        if let Some(reload) = reload {
+            self.request_reload(reload);
+        }
+        self.unsaved_changes_dialog(ctx);
+        if save_requested {
+            self.save_pac();
+        }
+        if let Some(format) = export_requested {
+            self.export_frame_data(format);
+        }
+        if let Some(sprite) = export_json_requested {
+            self.export_json(sprite);
+        }
+        if verify_requested {
+            self.verify_report = match self.col_promise.as_ref().and_then(|promise| promise.ready()) {
+                Some(Ok(col)) => Some(verify::verify(&col.bytes, self.game)),
+                _ => Some(Err("No pac is loaded".to_string())),
+            };
+        }
+        let mut show_report = self.verify_report.is_some();
+        egui::Window::new("Round-trip check")
+        .open(&mut show_report)
+        .show(ctx, |ui| {
+            if let Some(report) = &self.verify_report {
+                verify_report(ui, report);
+            }
         });
+        if !show_report {
+            self.verify_report = None;
+        }
+        if let Some(slot) = open_requested {
+            self.open_status = None;
+            self.pending_open = Some((slot, open::pick_file(slot, ctx)));
+        }
         if self.file_changed {
+            self.local_col_name = None;
             self.col_promise = None;
             self.char_promise = None;
             self.ef_promise = None;
         }
         if self.boxes_window.reset_image {
             self.image_promise = None;
+            self.image_error = None;
             self.boxes_window.image = Default::default();
             self.boxes_window.texture = Default::default();
         }
+        self.prefetch_images(ctx);
     }
 }
 
 impl MyApp {
     /// Called once before the first frame.
-    pub fn new(_cc: &eframe::CreationContext<'_>) -> Self {
+    pub fn new(cc: &eframe::CreationContext<'_>) -> Self {
         // This is also where you can customized the look at feel of egui using
         // `cc.egui_ctx.set_visuals` and `cc.egui_ctx.set_fonts`.
-        Default::default()
+        let mut app = Self::default();
+        if let Some(storage) = cc.storage {
+            if let Some(asset_source) = eframe::get_value(storage, ASSET_SOURCE_KEY) {
+                app.asset_source = asset_source;
+            }
+            if let Some(game) = eframe::get_value(storage, GAME_KEY) {
+                app.game = game;
+            }
+            if let Some(custom_characters) = eframe::get_value(storage, CUSTOM_CHARACTERS_KEY) {
+                app.custom_characters = custom_characters;
+            }
+            if let Some(legend) = eframe::get_value(storage, LEGEND_KEY) {
+                app.boxes_window.legend = legend;
+            }
+            if let Some(overlays) = eframe::get_value(storage, OVERLAYS_KEY) {
+                app.boxes_window.overlays = overlays;
+            }
+        }
+        app
+    }
+
+    /// Replaces one of the downloaded inputs with a file picked from disk. The file goes
+    /// through the same parsing as a download by being handed over as a ready promise.
+    fn open_local(&mut self, slot: Slot, file: LocalFile) {
+        match slot {
+            Slot::Collision => self.request_reload(PendingReload::Collision(file)),
+            Slot::CharScript => {
+                self.char_promise = Some(Promise::from_ready(Ok(file.bytes)));
+                self.char_script_error = None;
+                self.boxes_window.clear_char_script();
+            }
+            Slot::EffectScript => {
+                self.ef_promise = Some(Promise::from_ready(Ok(file.bytes)));
+                self.ef_script_error = None;
+                self.boxes_window.clear_ef_script();
+            }
+            Slot::GameDb => {
+                self.ron_promise = Some(Promise::from_ready(parse_ron(file.bytes)));
+                self.char_script_error = None;
+                self.ef_script_error = None;
+                self.boxes_window.clear_char_script();
+                self.boxes_window.clear_ef_script();
+            }
+            Slot::Image => {
+                self.image_promise = Some(Promise::from_ready(Ok(file.bytes)));
+                self.image_error = None;
+                self.boxes_window.image = None;
+                self.boxes_window.texture = None;
+            }
+            Slot::Json => match self.boxes_window.import_json(&file.bytes) {
+                Ok(changed) => self.save_status = Some(Ok(format!("Imported {}, {} sprite(s) changed", file.name, changed))),
+                Err(e) => self.open_status = Some(format!("Failed to import {}! {}", file.name, e)),
+            },
+        }
+    }
+
+    /// Carries out `reload` straight away if no boxes have been edited since the last save, and
+    /// otherwise asks first.
+    fn request_reload(&mut self, reload: PendingReload) {
+        if self.boxes_window.unsaved.is_empty() {
+            self.reload(reload);
+        }
+        else {
+            self.pending_reload = Some(reload);
+        }
+    }
+
+    fn reload(&mut self, reload: PendingReload) {
+        match reload {
+            PendingReload::Character(code) => {
+                self.selected = code;
+                self.default_selected = false;
+                self.loaded = false;
+                self.file_changed = true;
+            }
+            PendingReload::Game(game) => self.set_game(game),
+            PendingReload::AssetSource => {
+                self.asset_source = self.source_settings.clone();
+                self.roster_promise = None;
+                self.file_list = Default::default();
+                self.ron_promise = None;
+                self.image_promise = None;
+                self.loaded = false;
+                self.file_changed = true;
+            }
+            PendingReload::Collision(file) => {
+                self.col_promise = Some(Promise::from_ready(open::open_file(file.bytes, self.game).map_err(LoadError::Pac)));
+                self.local_col_name = Some(file.name);
+                self.loaded = false;
+            }
+        }
+    }
+
+    /// Asks whether to save, discard or keep the edited boxes before a pending reload.
+    fn unsaved_changes_dialog(&mut self, ctx: &egui::Context) {
+        let description = match &self.pending_reload {
+            Some(reload) => reload.description(),
+            None => return,
+        };
+        let mut save = false;
+        let mut discard = false;
+        let mut cancel = false;
+        egui::Window::new("Unsaved changes")
+        .collapsible(false)
+        .resizable(false)
+        .anchor(egui::Align2::CENTER_CENTER, egui::Vec2::ZERO)
+        .show(ctx, |ui| {
+            ui.label(format!(
+                "{} edited sprite(s) haven't been saved and will be lost by {}.",
+                self.boxes_window.unsaved.len(),
+                description
+            ));
+            ui.horizontal(|ui| {
+                save = ui.button("Save PAC…").clicked();
+                discard = ui.button("Discard changes").clicked();
+                cancel = ui.button("Cancel").clicked();
+            });
+        });
+        if save {
+            self.save_pac();
+            // Only go ahead once the edits are safely on disk, not if the dialog was cancelled.
+            discard = self.boxes_window.unsaved.is_empty();
+        }
+        if discard {
+            if let Some(reload) = self.pending_reload.take() {
+                self.reload(reload);
+            }
+        }
+        if cancel {
+            self.pending_reload = None;
+        }
+    }
+
+    /// Switches to another game's roster, command DB and asset directory and reloads everything.
+    fn set_game(&mut self, game: Game) {
+        if self.game == game {
+            return;
+        }
+        self.game = game;
+        self.file_list = Default::default();
+        self.roster_promise = None;
+        self.selected = Default::default();
+        self.ron_promise = None;
+        self.image_promise = None;
+        self.image_error = None;
+        self.loaded = false;
+        self.file_changed = true;
+    }
+
+    /// Fetches the renders of the playing state's sprites ahead of time and hands the finished
+    /// ones to the canvas, so playback doesn't wait for each image as it gets to it.
+    fn prefetch_images(&mut self, ctx: &egui::Context) {
+        for sprite in self.boxes_window.sprites_to_prefetch() {
+            if self.image_prefetch.contains_key(&sprite) {
+                continue;
+            }
+            let ctx = ctx.clone();
+            let (sender, promise) = Promise::new();
+            self.asset_source.fetch(&self.game.asset_path(&format!("images/{}/{}.png", self.selected, sprite)), move |response| {
+                sender.send(response);
+                ctx.request_repaint();
+            });
+            self.image_prefetch.insert(sprite, promise);
+        }
+        let fetched: Vec<(String, Vec<u8>)> = self
+            .image_prefetch
+            .iter()
+            .filter_map(|(sprite, promise)| match promise.ready() {
+                Some(Ok(bytes)) => Some((sprite.clone(), bytes.clone())),
+                _ => None,
+            })
+            .collect();
+        for (sprite, bytes) in fetched {
+            match self.boxes_window.bytes_to_image(&bytes) {
+                Ok(image) => {
+                    self.image_prefetch.remove(&sprite);
+                    self.boxes_window.textures.insert(sprite, ctx.load_texture("render", image));
+                }
+                // Failures stay in the map, so they aren't fetched again on every frame.
+                Err(e) => {
+                    self.image_prefetch.insert(sprite, Promise::from_ready(Err(LoadError::Decode(e.to_string()))));
+                }
+            }
+        }
+    }
+
+    /// Throws away a failed input so it is fetched and parsed again on the next frame.
+    fn retry(&mut self, slot: Slot) {
+        match slot {
+            Slot::Collision => {
+                self.col_promise = None;
+                self.loaded = false;
+            }
+            Slot::CharScript => {
+                self.char_promise = None;
+                self.char_script_error = None;
+                self.boxes_window.clear_char_script();
+            }
+            Slot::EffectScript => {
+                self.ef_promise = None;
+                self.ef_script_error = None;
+                self.boxes_window.clear_ef_script();
+            }
+            Slot::GameDb => {
+                self.ron_promise = None;
+                self.char_script_error = None;
+                self.ef_script_error = None;
+                self.boxes_window.clear_char_script();
+                self.boxes_window.clear_ef_script();
+            }
+            Slot::Image => {
+                self.image_promise = None;
+                self.image_error = None;
+                self.boxes_window.image = None;
+                self.boxes_window.texture = None;
+            }
+            // Nothing is downloaded for an import, so there is nothing to fetch again.
+            Slot::Json => (),
+        }
+    }
+
+    fn save_pac(&mut self) {
+        let file_name = self.local_col_name.clone().unwrap_or_else(|| format!("COL_{}.pac", self.selected));
+        self.save_status = match self.boxes_window.repack() {
+            Some(bytes) => match save::save_file(&file_name, &bytes) {
+                Ok(true) => {
+                    self.boxes_window.unsaved.clear();
+                    Some(Ok(format!("Saved {}", file_name)))
+                }
+                Ok(false) => None,
+                Err(e) => Some(Err(e)),
+            },
+            None => Some(Err("No pac is loaded".to_string())),
+        };
+    }
+
+    /// Exports `sprite`, or the whole PAC if `None`, as JSON.
+    fn export_json(&mut self, sprite: Option<String>) {
+        let file_name = match &sprite {
+            Some(sprite) => format!("{}.json", sprite),
+            None => {
+                let pac_name = self.local_col_name.clone().unwrap_or_else(|| format!("COL_{}.pac", self.selected));
+                let stem = pac_name.rsplit_once('.').map_or(pac_name.as_str(), |(stem, _)| stem);
+                format!("{}.json", stem)
+            }
+        };
+        self.save_status = match self.boxes_window.export_json(sprite.as_deref()) {
+            Ok(bytes) => match save::save_file(&file_name, &bytes) {
+                Ok(true) => Some(Ok(format!("Exported {}", file_name))),
+                Ok(false) => None,
+                Err(e) => Some(Err(e)),
+            },
+            Err(e) => Some(Err(e)),
+        };
+    }
+
+    fn export_frame_data(&mut self, format: TableFormat) {
+        let file_name = format!("{}_framedata.{}", self.selected, format.extension());
+        let rows = self.boxes_window.frame_table();
+        self.save_status = match format.write(&rows) {
+            Ok(bytes) => match save::save_file(&file_name, &bytes) {
+                Ok(true) => Some(Ok(format!("Exported frame data for {} moves to {}", rows.len(), file_name))),
+                Ok(false) => None,
+                Err(e) => Some(Err(e)),
+            },
+            Err(e) => Some(Err(e)),
+        };
     }
 }
 
-fn parse_col(response: ehttp::Response) -> Result<GGSTPac, String> {
-    let pac = open::open_file(response.bytes);
-    pac
+fn parse_ron(bytes: Vec<u8>) -> Result<GameDB, LoadError> {
+    GameDB::load(bytes).map_err(|e| LoadError::Script(e.to_string()))
 }
-fn parse_ron(response: ehttp::Response) -> Result<GameDB, BBScriptError> {
-    let ron = GameDB::load(response.bytes);
-    ron
+
+fn verify_report(ui: &mut egui::Ui, report: &Result<Report, String>) {
+    let report = match report {
+        Ok(report) => report,
+        Err(e) => {
+            ui.colored_label(egui::Color32::RED, format!("Could not check the pac! {}", e));
+            return;
+        }
+    };
+    if report.is_clean() {
+        ui.label(format!("The pac and its {} jonbin(s) are written back unchanged.", report.files.len()));
+    }
+    if let Some(difference) = &report.pac {
+        ui.colored_label(egui::Color32::RED, format!("The archive {}", difference));
+    }
+    egui::ScrollArea::vertical().max_height(400.0).show(ui, |ui| {
+        for (name, difference) in report.differing() {
+            ui.colored_label(egui::Color32::RED, format!("{} {}", name, difference));
+        }
+        for (name, e) in report.unparsed() {
+            ui.label(format!("{} was not checked: {}", name, e));
+        }
+    });
 }
-fn response_to_bytes(response: ehttp::Response) -> Vec<u8> {
-    response.bytes
+
+/// Shows why an input failed to load next to a Retry button. Returns true if Retry was clicked.
+fn load_error(ui: &mut egui::Ui, what: &str, error: &LoadError) -> bool {
+    ui.horizontal(|ui| {
+        ui.colored_label(egui::Color32::RED, format!("Failed to load {}! {}", what, error));
+        ui.button("Retry").clicked()
+    }).inner
 }
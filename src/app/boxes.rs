@@ -1,11 +1,17 @@
 use std::{path::{PathBuf}, io::Cursor};
-use arcsys::{ggst::{pac::{GGSTPac}, jonbin::{GGSTJonBin}}};
+use arcsys::{ggst::{pac::{GGSTPac}, jonbin::{GGSTJonBin, HitBox, Rect as JonbinRect}}};
 use eframe::{egui::{self, Sense, Frame}, emath::{Rect, Pos2, Vec2}, epaint::{Color32, Stroke, ColorImage, Mesh, TextureId, Shape}};
 use image::{ImageError};
-use serde::{Serialize, Deserialize};
-use std::collections::{BTreeMap};
-use substring::Substring;
+use std::collections::{BTreeMap, BTreeSet};
 use image::io::Reader as ImageReader;
+use super::framedata::{self, FrameData, Row};
+use super::game::{BoxShape, BoxType, Game};
+use super::history::{Edit, History};
+use super::json::Document;
+use super::legend::Legend;
+use super::overlay::{Overlays, Ruler};
+use super::view::{Transform, IMAGE_ORIGIN, ZOOM_PRESETS};
+use super::states::{self, State};
 
 #[derive(serde::Deserialize, serde::Serialize)]
 struct Box {
@@ -15,11 +21,6 @@ struct Box {
     h: String,
 }
 
-#[derive(Serialize, Deserialize)]
-enum MetaKind {
-    Pac(GGSTPac),
-}
-
 impl Default for Box {
     fn default() -> Self {
         Self {
@@ -31,83 +32,169 @@ impl Default for Box {
     }
 }
 
-#[derive(Copy, Clone)]
-enum BoxType {
-    Hurtbox = 0,
-    Hitbox = 1,
-    ExPoint = 2,
-    ExRect = 3,
-    ExVector = 4,
-    Push = 5,
-    TempCenter = 6,
-    Neck = 7,
-    Abdominal = 8,
-    AttackVsPush = 9,
-    SpGuard = 10,
-    RLeg = 11,
-    LLeg = 12,
-    Private0 = 13,
-    Private1 = 14,
-    Private2 = 15,
-    Private3 = 16,
-    ExtendJon = 17,
+impl From<&HitBox> for Box {
+    fn from(hitbox: &HitBox) -> Self {
+        Self {
+            x: hitbox.rect.x_offset.to_string(),
+            y: hitbox.rect.y_offset.to_string(),
+            w: hitbox.rect.width.to_string(),
+            h: hitbox.rect.height.to_string(),
+        }
+    }
 }
 
-impl TryFrom<u32> for BoxType {
-    type Error = ();
-
-    fn try_from(v: u32) -> Result<Self, Self::Error> {
-        match v {
-            x if x == BoxType::Hurtbox as u32 => Ok(BoxType::Hurtbox),
-            x if x == BoxType::Hitbox as u32 => Ok(BoxType::Hitbox),
-            x if x == BoxType::ExPoint as u32 => Ok(BoxType::ExPoint),
-            x if x == BoxType::ExRect as u32 => Ok(BoxType::ExRect),
-            x if x == BoxType::ExVector as u32 => Ok(BoxType::ExVector),
-            x if x == BoxType::Push as u32 => Ok(BoxType::Push),
-            x if x == BoxType::TempCenter as u32 => Ok(BoxType::TempCenter),
-            x if x == BoxType::Neck as u32 => Ok(BoxType::Neck),
-            x if x == BoxType::Abdominal as u32 => Ok(BoxType::Abdominal),
-            x if x == BoxType::AttackVsPush as u32 => Ok(BoxType::AttackVsPush),
-            x if x == BoxType::SpGuard as u32 => Ok(BoxType::SpGuard),
-            x if x == BoxType::RLeg as u32 => Ok(BoxType::RLeg),
-            x if x == BoxType::LLeg as u32 => Ok(BoxType::LLeg),
-            x if x == BoxType::Private0 as u32 => Ok(BoxType::Private0),
-            x if x == BoxType::Private1 as u32 => Ok(BoxType::Private1),
-            x if x == BoxType::Private2 as u32 => Ok(BoxType::Private2),
-            x if x == BoxType::Private3 as u32 => Ok(BoxType::Private3),
-            x if x == BoxType::ExtendJon as u32 => Ok(BoxType::ExtendJon),
-            _ => Err(()),
-        }
+/// Describes a box kind for display, falling back to the raw value for kinds this editor doesn't know.
+fn kind_name(kind: u32) -> String {
+    match BoxType::try_from(kind) {
+        Ok(box_type) => box_type.name().to_string(),
+        Err(_) => format!("Unknown ({})", kind),
     }
 }
 
+/// Distance in screen pixels from a box edge that still grabs the edge for resizing.
+const HANDLE_SIZE: f32 = 6.0;
+
+/// Half the length, in screen pixels, of the crosshair drawn for a point.
+const CROSSHAIR_SIZE: f32 = 8.0;
+
+/// How fast the mouse wheel zooms the canvas, per unit of scroll.
+const WHEEL_ZOOM_SPEED: f32 = 0.002;
+
+/// Frame rate of the game, used to play states back in real time.
+const GAME_FPS: f64 = 60.0;
+
+/// Position in the sprite sequence of the current state.
+#[derive(Default)]
+struct Playback {
+    /// Counting from 0.
+    frame: u32,
+    playing: bool,
+    /// Time since the last frame was shown, in seconds.
+    elapsed: f64,
+}
+
+/// A file in the PAC that couldn't be read as a jonbin.
+pub struct ParseFailure {
+    pub name: String,
+    /// Where the file starts in the PAC, if the file table could be read.
+    pub offset: Option<u64>,
+    pub error: String,
+}
+
+#[derive(Copy, Clone, PartialEq)]
+enum DragMode {
+    Move,
+    Resize {
+        left: bool,
+        right: bool,
+        top: bool,
+        bottom: bool,
+    },
+    /// Moves the tip of a vector, leaving its start in place.
+    Tip,
+}
+
 #[derive(Default)]
 pub struct BoxesWindow {
     path: PathBuf,
+    pac: Option<GGSTPac>,
     pub jonbins: BTreeMap<String, GGSTJonBin>,
+    /// Sprites edited since the PAC was loaded, whose jonbins `repack` writes back.
+    pub modified: BTreeSet<String>,
+    /// Sprites edited since the PAC was loaded or last saved.
+    pub unsaved: BTreeSet<String>,
+    pub parse_failures: Vec<ParseFailure>,
     pub selected: String,
-    offset_x: f32,
-    offset_y: f32,
+    view: Transform,
+    /// Which box kinds are drawn and how. Saved with the app's settings.
+    pub legend: Legend,
+    /// Axes, ground and grid drawn behind the boxes. Saved with the app's settings.
+    pub overlays: Overlays,
+    ruler: Ruler,
+    /// Where the canvas was drawn last frame, for the zoom controls above it.
+    canvas: Rect,
     last_cursor_pos: Pos2,
+    selected_box: Option<(usize, usize)>,
+    drag_mode: Option<DragMode>,
+    drag_start: Option<(String, Vec<Vec<HitBox>>, Option<(usize, usize)>)>,
+    new_box_kind: BoxType,
     box_info: Box,
+    box_info_source: Option<(String, usize, usize)>,
+    box_info_errors: [Option<String>; 4],
     current_name: String,
-    pub is_gbvs: bool,
+    pub game: Game,
     pub char_script: String,
     pub ef_script: String,
-    states: BTreeMap<String, String>,
-    ef_states: BTreeMap<String, String>,
-    current_state: (String, String),
+    states: BTreeMap<String, State>,
+    ef_states: BTreeMap<String, State>,
+    char_script_errors: Vec<String>,
+    ef_script_errors: Vec<String>,
+    current_state: Option<State>,
+    playback: Playback,
     is_ef: bool,
     show_state_list: bool,
     show_state: bool,
     pub box_changed: bool,
     pub image: Option<ColorImage>,
     pub texture: Option<egui::TextureHandle>,
+    /// Renders of the sprites shown so far, by sprite name, so that going back to one (which
+    /// playback does all the time) doesn't fetch and decode it again.
+    pub textures: BTreeMap<String, egui::TextureHandle>,
     pub reset_image: bool,
+    history: History,
 }
 
 impl BoxesWindow {
+    fn diagnostics(&self, ui: &mut egui::Ui) {
+        egui::CollapsingHeader::new(
+            egui::RichText::new(format!("⚠ {} file(s) in the PAC could not be parsed, so it is only partially loaded", self.parse_failures.len()))
+            .color(Color32::RED)
+        )
+        .id_source("parse_failures")
+        .show(ui, |ui| {
+            egui::ScrollArea::vertical().max_height(150.0).show(ui, |ui| {
+                egui::Grid::new("parse_failure_grid").striped(true).show(ui, |ui| {
+                    ui.strong("File");
+                    ui.strong("Offset");
+                    ui.strong("Error");
+                    ui.end_row();
+                    for failure in &self.parse_failures {
+                        ui.label(&failure.name);
+                        ui.label(failure.offset.map_or_else(|| "?".to_string(), |offset| format!("{:#x}", offset)));
+                        ui.label(&failure.error);
+                        ui.end_row();
+                    }
+                });
+            });
+        });
+    }
+
+    fn failed_to_parse(&self, name: &str) -> bool {
+        self.parse_failures.iter().any(|failure| failure.name == name)
+    }
+
     pub fn ui(&mut self, ui: &mut egui::Ui) {
+        if !ui.ctx().wants_keyboard_input() {
+            let (command, shift, z_pressed, y_pressed) = {
+                let input = ui.input();
+                (input.modifiers.command, input.modifiers.shift, input.key_pressed(egui::Key::Z), input.key_pressed(egui::Key::Y))
+            };
+            if command && z_pressed && !shift {
+                self.undo();
+            }
+            else if command && ((z_pressed && shift) || y_pressed) {
+                self.redo();
+            }
+        }
+        if !self.parse_failures.is_empty() {
+            self.diagnostics(ui);
+        }
+        for error in &self.char_script_errors {
+            ui.colored_label(Color32::YELLOW, format!("Character script: {}", error));
+        }
+        for error in &self.ef_script_errors {
+            ui.colored_label(Color32::YELLOW, format!("Effect script: {}", error));
+        }
         ui.horizontal(|ui| {
             ui.checkbox(&mut self.is_ef, "Effect States");
             ui.checkbox(&mut self.show_state_list, "Show state list");
@@ -127,7 +214,8 @@ impl BoxesWindow {
                                     if ui.selectable_label(true, name)
                                     .clicked()
                                     {
-                                        self.current_state = (name.clone(), state.clone());
+                                        self.current_state = Some(state.clone());
+                                        self.playback = Default::default();
                                         self.selected = "".to_string();
                                         self.current_name = "".to_string();    
                                     };
@@ -138,7 +226,8 @@ impl BoxesWindow {
                                     if ui.selectable_label(true, name)
                                     .clicked()
                                     {
-                                        self.current_state = (name.clone(), state.clone());
+                                        self.current_state = Some(state.clone());
+                                        self.playback = Default::default();
                                         self.selected = "".to_string();
                                         self.current_name = "".to_string();                        
                                     };
@@ -150,16 +239,79 @@ impl BoxesWindow {
             }
             if self.show_state {
                 ui.vertical(|ui|{
-                    if self.current_state.0 != "" {
+                    if self.current_state.is_some() {
                         self.display_state(ui);
                     }
                 });
             };
+            if self.selected != "" && self.selected_box.is_some() {
+                ui.vertical(|ui| {
+                    ui.set_width(200.0);
+                    self.box_inspector(ui);
+                });
+            }
             ui.vertical(|ui|{
                 if self.selected != ""{
                     ui.label(format!("Selected sprite: {}", self.selected));
-                    ui.label("You can click and drag the canvas to move around!
-Double click to reset to the original position.");
+                    if self.failed_to_parse(&self.selected) {
+                        ui.colored_label(Color32::RED, "This sprite's collision data could not be parsed, see the list of failed files above.");
+                    }
+                    ui.label("You can click and drag the canvas to move around, and scroll to zoom!
+Double click to reset to the original position.
+Click a box to select it, drag it to move it, or drag its edges and corners to resize it.");
+                    ui.horizontal(|ui| {
+                        if ui.button("Add box").clicked() {
+                            self.insert_box(self.new_box_kind);
+                        }
+                        egui::ComboBox::from_id_source("new_box_kind")
+                        .selected_text(self.new_box_kind.name())
+                        .show_ui(ui, |ui| {
+                            for &kind in self.game.box_types() {
+                                ui.selectable_value(&mut self.new_box_kind, kind, kind.name());
+                            }
+                        });
+                        if ui.add_enabled(self.selected_box.is_some(), egui::Button::new("Delete box")).clicked() {
+                            self.delete_selected_box();
+                        }
+                        ui.separator();
+                        egui::ComboBox::from_id_source("zoom")
+                        .selected_text(format!("{:.0}%", self.view.zoom * 100.0))
+                        .show_ui(ui, |ui| {
+                            for zoom in ZOOM_PRESETS {
+                                if ui.selectable_label(self.view.zoom == zoom, format!("{:.0}%", zoom * 100.0)).clicked() {
+                                    self.view.zoom_around(self.canvas.center(), zoom);
+                                }
+                            }
+                        });
+                        if ui.button("Fit to boxes").clicked() {
+                            self.fit_to_boxes();
+                        }
+                        ui.menu_button("Overlays", |ui| {
+                            self.overlays.ui(ui);
+                        });
+                        if ui.selectable_label(self.ruler.active, "Ruler")
+                        .on_hover_text("Click two points on the canvas to measure the distance between them")
+                        .clicked()
+                        {
+                            self.ruler.active = !self.ruler.active;
+                            self.ruler.clear();
+                        }
+                    });
+                    if self.selected_box.is_some()
+                        && !ui.ctx().wants_keyboard_input()
+                        && ui.input().key_pressed(egui::Key::Delete)
+                    {
+                        self.delete_selected_box();
+                    }
+                    egui::CollapsingHeader::new("Legend").show(ui, |ui| {
+                        let mut counts = BTreeMap::new();
+                        if let Some(jonb) = self.jonbins.get(&self.selected) {
+                            for hitbox in jonb.boxes.iter().flatten() {
+                                *counts.entry(hitbox.kind).or_insert(0) += 1;
+                            }
+                        }
+                        self.legend.ui(ui, self.game, &counts);
+                    });
                     Frame::canvas(ui.style()).show(ui, |ui| {
                         self.render_boxes(ui);
                     });
@@ -185,220 +337,749 @@ Double click to reset to the original position.");
                 },
                 Sense::click_and_drag()
             );
+            self.canvas = response.rect;
+            let view = self.view;
+            if response.drag_started() {
+                self.drag_mode = None;
+                if let Some(pointer_pos) = response.interact_pointer_pos().filter(|_| !self.ruler.active) {
+                    if let Some(((group, index), mode)) = box_at(&jonb.boxes, self.selected_box, pointer_pos, &view, |kind| self.legend.is_visible(self.game, kind)) {
+                        let verb = match mode {
+                            DragMode::Move => "Move",
+                            DragMode::Resize { .. } | DragMode::Tip => "Resize",
+                        };
+                        let description = format!("{} {}", verb, kind_name(jonb.boxes[group][index].kind));
+                        self.drag_start = Some((description, jonb.boxes.clone(), self.selected_box));
+                        self.selected_box = Some((group, index));
+                        self.drag_mode = Some(mode);
+                    }
+                }
+            }
+            if response.clicked() && self.ruler.active {
+                if let Some(pointer_pos) = response.interact_pointer_pos() {
+                    self.ruler.click(view.to_world(pointer_pos));
+                }
+            }
+            else if response.clicked() {
+                self.selected_box = response.interact_pointer_pos()
+                    .and_then(|pointer_pos| box_at(&jonb.boxes, self.selected_box, pointer_pos, &view, |kind| self.legend.is_visible(self.game, kind)))
+                    .map(|(hit, _)| hit);
+            }
             if let Some(pointer_pos) = response.interact_pointer_pos() {
                 if self.last_cursor_pos != Default::default()
                 {
                     let pointer_delta = pointer_pos - self.last_cursor_pos;
-                    self.offset_x += pointer_delta.x;
-                    self.offset_y += pointer_delta.y;
+                    match (self.drag_mode, self.selected_box) {
+                        (Some(mode), Some((group, index))) => {
+                            if let Some(hitbox) = jonb.boxes.get_mut(group).and_then(|g| g.get_mut(index)) {
+                                drag_box(hitbox, mode, pointer_delta / view.zoom);
+                                self.modified.insert(self.selected.clone());
+                                self.unsaved.insert(self.selected.clone());
+                            }
+                        }
+                        _ => {
+                            self.view.origin += pointer_delta;
+                        }
+                    }
                     response.mark_changed();
                 }
                 self.last_cursor_pos = pointer_pos;
             }
             else {
                 self.last_cursor_pos = Default::default();
+                self.drag_mode = None;
+                if let Some((description, before, selected_before)) = self.drag_start.take() {
+                    // Clicking a box without moving it shouldn't leave an entry in the history.
+                    if !boxes_eq(&before, &jonb.boxes) {
+                        self.history.push(Edit {
+                            sprite: self.selected.clone(),
+                            description,
+                            before,
+                            after: jonb.boxes.clone(),
+                            selected_before,
+                            selected_after: self.selected_box,
+                            mergeable: false,
+                        });
+                    }
+                }
             }
             if self.box_changed {
-                self.offset_x = width * 0.8;
-                self.offset_y = 802.0;
+                self.view = Transform { origin: Pos2 { x: width * 0.8, y: 802.0 }, zoom: 1.0 };
                 self.box_changed = false;
             }
             if response.double_clicked()
             {
-                self.offset_x = width * 0.8;
-                self.offset_y = 802.0;
+                self.view = Transform { origin: Pos2 { x: width * 0.8, y: 802.0 }, zoom: 1.0 };
             }
+            if let Some(pointer_pos) = response.hover_pos() {
+                let scroll = ui.input().scroll_delta.y;
+                if scroll != 0.0 {
+                    self.view.zoom_around(pointer_pos, self.view.zoom * (scroll * WHEEL_ZOOM_SPEED).exp());
+                }
+            }
+            let view = self.view;
 
-            if self.image.is_some() {
-                let image = self.image.as_ref().unwrap();
-                if image.width() != 0 && image.height() != 0 {
-                    let texture: &egui::TextureHandle = self.texture.get_or_insert_with(|| {
-                        // Load the texture only once.
-                        ui.ctx().load_texture("render", image.clone())
-                    });
-                    let mut mesh = Mesh::with_texture(TextureId::from(texture));
-                    let pos = Pos2{x: 0.0, y: 0.0};
-                    let uv = Pos2{x: 0.0, y: 0.0};
-                    let max = Vec2{x: 1920.0, y: 1080.0};
-                    let rect = Rect::from_min_size(pos, max);
-                    let uv = Rect::from_min_size(uv, max);
-                    mesh.add_rect_with_uv(rect, uv, Color32::WHITE);
-                    painter.add(Shape::mesh(mesh));
+            if self.texture.is_none() {
+                if let Some(image) = self.image.as_ref().filter(|image| image.width() != 0 && image.height() != 0) {
+                    // Load the texture only once per sprite.
+                    let texture = ui.ctx().load_texture("render", image.clone());
+                    self.textures.insert(self.selected.clone(), texture.clone());
+                    self.texture = Some(texture);
                 }
             }
+            if let Some(texture) = &self.texture {
+                let mut mesh = Mesh::with_texture(TextureId::from(texture));
+                let rect = view.rect_to_screen(Rect::from_min_size((-IMAGE_ORIGIN).to_pos2(), texture.size_vec2()));
+                let uv = Rect::from_min_max(Pos2 { x: 0.0, y: 0.0 }, Pos2 { x: 1.0, y: 1.0 });
+                mesh.add_rect_with_uv(rect, uv, Color32::WHITE);
+                painter.add(Shape::mesh(mesh));
+            }
+            self.overlays.paint(&painter, &view, response.rect);
 
-            for boxgroup in &mut jonb.boxes {
-                for hitbox in boxgroup {
-                    let mut color = Color32::GREEN;
-                    match hitbox.kind.try_into(){
-                        Ok(BoxType::Hurtbox) => {
-                            color = Color32::GREEN;
-                            "Hurtbox"},
-                        Ok(BoxType::Hitbox) => {
-                            color = Color32::RED;
-                            "Hitbox"},
-                        Ok(BoxType::ExPoint) => {
-                            color = Color32::BLUE;
-                            "Hitbox"},
-                        Ok(BoxType::ExRect) => {
-                            color = Color32::GOLD;
-                            "ExRect"},
-                        Ok(BoxType::ExVector) => {
-                            color = Color32::YELLOW;
-                            "ExVector"},
-                        Ok(BoxType::Push) => {
-                            color = Color32::DARK_BLUE;
-                            "Push"},
-                        Ok(BoxType::TempCenter) => {
-                            color = Color32::LIGHT_GREEN;
-                            "TempCenter"},
-                        Ok(BoxType::Neck) => {
-                            color = Color32::LIGHT_RED;
-                            "Neck"},
-                        Ok(BoxType::Abdominal) => {
-                            color = Color32::LIGHT_BLUE;
-                            "Abdominal"},
-                        Ok(BoxType::AttackVsPush) => {
-                            color = Color32::LIGHT_YELLOW;
-                            "AttackVsPush"},
-                        Ok(BoxType::SpGuard) => {
-                            color = Color32::DEBUG_COLOR;
-                            "SpGuard"},
-                        Ok(BoxType::RLeg) => {
-                            color = Color32::KHAKI;
-                            "RLeg"},
-                        Ok(BoxType::LLeg) => {
-                            color = Color32::BROWN;
-                            "LLeg"},
-                        Ok(BoxType::Private0) => {
-                            color = Color32::GRAY;
-                            "LLeg"},
-                        Ok(BoxType::Private1) => {
-                            color = Color32::BLACK;
-                            "LLeg"},
-                        Ok(BoxType::Private2) => {
-                            color = Color32::LIGHT_GRAY;
-                            "LLeg"},
-                        Ok(BoxType::Private3) => {
-                            color = Color32::DARK_GRAY;
-                            "SpGuard"},
-                        Ok(BoxType::ExtendJon)  => {
-                            color = Color32::DARK_RED;
-                            "ExtendJon"},
-                        Err(_) => ""
+            for (group_index, boxgroup) in jonb.boxes.iter().enumerate() {
+                for (box_index, hitbox) in boxgroup.iter().enumerate() {
+                    let (color, fill) = match BoxType::try_from(hitbox.kind) {
+                        Ok(kind) => {
+                            let style = self.legend.style(self.game, kind);
+                            if !style.visible {
+                                continue;
+                            }
+                            (style.stroke_color(), style.fill_color())
+                        }
+                        Err(_) => (Color32::GREEN, None),
                     };
+                    let selected = self.selected_box == Some((group_index, box_index));
+                    match box_shape(hitbox) {
+                        BoxShape::Point => {
+                            let point = view.to_screen(box_start(hitbox));
+                            let stroke = Stroke{width: 2.0, color};
+                            painter.line_segment([point - Vec2::X * CROSSHAIR_SIZE, point + Vec2::X * CROSSHAIR_SIZE], stroke);
+                            painter.line_segment([point - Vec2::Y * CROSSHAIR_SIZE, point + Vec2::Y * CROSSHAIR_SIZE], stroke);
+                            if selected {
+                                painter.circle_stroke(point, CROSSHAIR_SIZE + 2.0, Stroke{width: 1.0, color: Color32::WHITE});
+                            }
+                            continue;
+                        }
+                        BoxShape::Vector => {
+                            let start = view.to_screen(box_start(hitbox));
+                            let tip = view.to_screen(box_tip(hitbox));
+                            painter.circle_filled(start, 3.0, color);
+                            painter.arrow(start, tip - start, Stroke{width: 2.0, color});
+                            if selected {
+                                painter.circle_stroke(start, HANDLE_SIZE, Stroke{width: 1.0, color: Color32::WHITE});
+                                painter.rect_filled(Rect::from_center_size(tip, Vec2::splat(HANDLE_SIZE)), 0.0, Color32::WHITE);
+                            }
+                            continue;
+                        }
+                        BoxShape::Rect => (),
+                    }
+                    let rect = box_screen_rect(hitbox, &view);
+                    if let Some(fill) = fill {
+                        painter.rect_filled(rect, 0.0, fill);
+                    }
                     painter.rect_stroke(
-                        Rect { min: Pos2{x: (hitbox.rect.x_offset + self.offset_x ), 
-                            y: (hitbox.rect.y_offset + self.offset_y)}, 
-                            max: Pos2{x: (hitbox.rect.x_offset + hitbox.rect.width + self.offset_x), 
-                            y: (hitbox.rect.y_offset + hitbox.rect.height + self.offset_y)} },
+                        rect,
                         0.0, 
                         Stroke{width: 3.0, color},
                     );
+                    if selected {
+                        painter.rect_stroke(rect.expand(2.0), 0.0, Stroke{width: 1.0, color: Color32::WHITE});
+                        for corner in [rect.left_top(), rect.right_top(), rect.left_bottom(), rect.right_bottom()] {
+                            painter.rect_filled(
+                                Rect::from_center_size(corner, Vec2::splat(HANDLE_SIZE)),
+                                0.0,
+                                Color32::WHITE,
+                            );
+                        }
+                    }
+                }
+            }
+
+            self.ruler.paint(&painter, &view, response.hover_pos());
+
+            if let Some(pointer_pos) = response.hover_pos() {
+                let world = view.to_world(pointer_pos);
+                painter.text(
+                    response.rect.left_bottom() + Vec2{x: 6.0, y: -6.0},
+                    egui::Align2::LEFT_BOTTOM,
+                    format!("x: {:.0}  y: {:.0}", world.x, world.y),
+                    egui::FontId::monospace(14.0),
+                    ui.visuals().text_color(),
+                );
+                // Every box under the pointer is listed, topmost first, since overlapping boxes
+                // can't be told apart by looking at them.
+                let hovered = boxes_at(&jonb.boxes, pointer_pos, &view, |kind| self.legend.is_visible(self.game, kind));
+                if !hovered.is_empty() && self.drag_mode.is_none() {
+                    egui::show_tooltip_at_pointer(ui.ctx(), egui::Id::new("box_tooltip"), |ui| {
+                        for ((group, index), _) in hovered {
+                            let hitbox = &jonb.boxes[group][index];
+                            ui.strong(format!("{} (group {}, box {})", kind_name(hitbox.kind), group, index));
+                            ui.label(format!("x_offset: {}\ny_offset: {}\nwidth: {}\nheight: {}",
+                                hitbox.rect.x_offset, hitbox.rect.y_offset, hitbox.rect.width, hitbox.rect.height));
+                        }
+                    });
+                }
+            }
+        }
+    }
+
+    /// Zooms and pans the canvas so every box of the selected sprite is in view.
+    fn fit_to_boxes(&mut self) {
+        let bounds = self.jonbins
+            .get(&self.selected)
+            .into_iter()
+            .flat_map(|jonb| jonb.boxes.iter().flatten())
+            .map(box_world_rect)
+            .reduce(|a, b| a.union(b));
+        if let Some(bounds) = bounds {
+            self.view.fit(bounds, self.canvas);
+        }
+    }
+
+    fn box_inspector(&mut self, ui: &mut egui::Ui) {
+        let (group, index) = match self.selected_box {
+            Some(selected_box) => selected_box,
+            None => return,
+        };
+        let source = Some((self.selected.clone(), group, index));
+        let mut new_kind = None;
+        let mut new_value = None;
+        {
+            let hitbox = match self.jonbins.get(&self.selected).and_then(|jonb| jonb.boxes.get(group)).and_then(|g| g.get(index)) {
+                Some(hitbox) => hitbox,
+                None => return,
+            };
+            // Refresh the text fields when another box is selected or this one is being dragged,
+            // but otherwise leave them alone so a half-typed value isn't overwritten.
+            if self.box_info_source != source || self.drag_mode.is_some() {
+                self.box_info = Box::from(hitbox);
+                self.box_info_errors = Default::default();
+                self.box_info_source = source;
+            }
+
+            ui.heading("Selected box");
+            ui.label(format!("Group {}, box {}", group, index));
+            egui::Grid::new("box_inspector").num_columns(2).show(ui, |ui| {
+                let fields = [
+                    ("X", &mut self.box_info.x),
+                    ("Y", &mut self.box_info.y),
+                    ("Width", &mut self.box_info.w),
+                    ("Height", &mut self.box_info.h),
+                ];
+                for (field, ((label, text), error)) in fields.into_iter().zip(self.box_info_errors.iter_mut()).enumerate() {
+                    ui.label(label);
+                    if ui.text_edit_singleline(text).changed() {
+                        match text.trim().parse::<f32>() {
+                            Ok(parsed) if parsed.is_finite() => {
+                                new_value = Some((field, parsed));
+                                *error = None;
+                            }
+                            Ok(_) => *error = Some(format!("{} must be a finite number", label)),
+                            Err(e) => *error = Some(format!("\"{}\" is not a number: {}", text, e)),
+                        }
+                    }
+                    ui.end_row();
+                    if let Some(error) = error {
+                        ui.label("");
+                        ui.colored_label(Color32::RED, error.as_str());
+                        ui.end_row();
+                    }
+                }
+
+                ui.label("Type");
+                let current_kind = BoxType::try_from(hitbox.kind).ok();
+                let mut selected_kind = current_kind;
+                egui::ComboBox::from_id_source("box_kind")
+                .selected_text(kind_name(hitbox.kind))
+                .show_ui(ui, |ui| {
+                    for &kind in self.game.box_types() {
+                        ui.selectable_value(&mut selected_kind, Some(kind), kind.name());
+                    }
+                });
+                if selected_kind != current_kind {
+                    new_kind = selected_kind;
+                }
+                ui.end_row();
+            });
+        }
+        if let Some((field, value)) = new_value {
+            let before = self.snapshot();
+            if let Some(hitbox) = self.jonbins.get_mut(&self.selected).and_then(|jonb| jonb.boxes.get_mut(group)).and_then(|g| g.get_mut(index)) {
+                match field {
+                    0 => hitbox.rect.x_offset = value,
+                    1 => hitbox.rect.y_offset = value,
+                    2 => hitbox.rect.width = value,
+                    _ => hitbox.rect.height = value,
+                }
+                let description = format!("Edit {}", kind_name(hitbox.kind));
+                self.record(description, before, self.selected_box, true);
+            }
+        }
+        if let Some(kind) = new_kind {
+            self.set_box_kind(kind);
+        }
+    }
+
+    /// Adds a new box of `kind` to the current sprite, just above the character origin, and selects it.
+    fn insert_box(&mut self, kind: BoxType) {
+        let before = self.snapshot();
+        let selected_before = self.selected_box;
+        if let Some(jonb) = self.jonbins.get_mut(&self.selected) {
+            let group = kind as usize;
+            if jonb.boxes.len() <= group {
+                jonb.boxes.resize_with(group + 1, Vec::new);
+            }
+            jonb.boxes[group].push(HitBox {
+                kind: kind as u32,
+                rect: JonbinRect {
+                    x_offset: -50.0,
+                    y_offset: -150.0,
+                    width: 100.0,
+                    height: 150.0,
+                },
+            });
+            self.selected_box = Some((group, jonb.boxes[group].len() - 1));
+            self.record(format!("Add {}", kind.name()), before, selected_before, false);
+        }
+    }
+
+    fn delete_selected_box(&mut self) {
+        let (group, index) = match self.selected_box {
+            Some(selected_box) => selected_box,
+            None => return,
+        };
+        let before = self.snapshot();
+        if let Some(boxgroup) = self.jonbins.get_mut(&self.selected).and_then(|jonb| jonb.boxes.get_mut(group)) {
+            if index < boxgroup.len() {
+                let hitbox = boxgroup.remove(index);
+                self.selected_box = None;
+                self.record(format!("Delete {}", kind_name(hitbox.kind)), before, Some((group, index)), false);
+            }
+        }
+        self.selected_box = None;
+    }
+
+    /// Changes the kind of the selected box. A jonbin keeps one box group per `BoxType` and
+    /// the counts in its header come from the group lengths, so the box is moved into the
+    /// group for its new kind rather than only having `kind` rewritten.
+    fn set_box_kind(&mut self, kind: BoxType) {
+        let (group, index) = match self.selected_box {
+            Some(selected_box) => selected_box,
+            None => return,
+        };
+        let before = self.snapshot();
+        if let Some(jonb) = self.jonbins.get_mut(&self.selected) {
+            if index >= jonb.boxes.get(group).map_or(0, |g| g.len()) {
+                return;
+            }
+            let mut hitbox = jonb.boxes[group].remove(index);
+            let description = format!("Change {} to {}", kind_name(hitbox.kind), kind.name());
+            hitbox.kind = kind as u32;
+            let new_group = kind as usize;
+            if jonb.boxes.len() <= new_group {
+                jonb.boxes.resize_with(new_group + 1, Vec::new);
+            }
+            jonb.boxes[new_group].push(hitbox);
+            self.selected_box = Some((new_group, jonb.boxes[new_group].len() - 1));
+            self.record(description, before, Some((group, index)), false);
+        }
+    }
+
+    /// The box groups of the current sprite, taken before an edit so it can be undone.
+    fn snapshot(&self) -> Vec<Vec<HitBox>> {
+        self.jonbins.get(&self.selected).map(|jonb| jonb.boxes.clone()).unwrap_or_default()
+    }
+
+    /// Adds the change from `before` to the current sprite's boxes to the undo history.
+    fn record(&mut self, description: String, before: Vec<Vec<HitBox>>, selected_before: Option<(usize, usize)>, mergeable: bool) {
+        if let Some(jonb) = self.jonbins.get(&self.selected) {
+            self.history.push(Edit {
+                sprite: self.selected.clone(),
+                description,
+                before,
+                after: jonb.boxes.clone(),
+                selected_before,
+                selected_after: self.selected_box,
+                mergeable,
+            });
+            self.modified.insert(self.selected.clone());
+            self.unsaved.insert(self.selected.clone());
+        }
+    }
+
+    pub fn undo(&mut self) {
+        if let Some(edit) = self.history.undo() {
+            let (sprite, boxes, selected_box) = (edit.sprite.clone(), edit.before.clone(), edit.selected_before);
+            self.restore(sprite, boxes, selected_box);
+        }
+    }
+
+    pub fn redo(&mut self) {
+        if let Some(edit) = self.history.redo() {
+            let (sprite, boxes, selected_box) = (edit.sprite.clone(), edit.after.clone(), edit.selected_after);
+            self.restore(sprite, boxes, selected_box);
+        }
+    }
+
+    /// Puts `boxes` back into `sprite` and switches the canvas over to it.
+    fn restore(&mut self, sprite: String, boxes: Vec<Vec<HitBox>>, selected_box: Option<(usize, usize)>) {
+        if let Some(jonb) = self.jonbins.get_mut(&sprite) {
+            jonb.boxes = boxes;
+        }
+        if self.selected != sprite {
+            self.selected = sprite.clone();
+            self.reset_image = true;
+            self.image = None;
+        }
+        self.selected_box = selected_box;
+        self.box_info_source = None;
+        self.drag_mode = None;
+        self.drag_start = None;
+        self.modified.insert(sprite.clone());
+        self.unsaved.insert(sprite);
+    }
+
+    /// Contents of the Edit menu: undo/redo plus the history, where clicking an entry
+    /// undoes or redoes back to the state right after it.
+    pub fn edit_menu(&mut self, ui: &mut egui::Ui) {
+        if ui.add_enabled(self.history.can_undo(), egui::Button::new("Undo (Ctrl+Z)")).clicked() {
+            self.undo();
+            ui.close_menu();
+        }
+        if ui.add_enabled(self.history.can_redo(), egui::Button::new("Redo (Ctrl+Shift+Z)")).clicked() {
+            self.redo();
+            ui.close_menu();
+        }
+        ui.separator();
+        let mut undo_count = 0;
+        let mut redo_count = 0;
+        egui::ScrollArea::vertical().max_height(300.0).show(ui, |ui| {
+            for (steps, edit) in self.history.redo_list().iter().enumerate() {
+                let label = format!("{} ({})", edit.description, edit.sprite);
+                if ui.add(egui::Button::new(egui::RichText::new(label).weak())).clicked() {
+                    redo_count = self.history.redo_list().len() - steps;
+                }
+            }
+            for (steps, edit) in self.history.undo_list().iter().rev().enumerate() {
+                if ui.button(format!("{} ({})", edit.description, edit.sprite)).clicked() {
+                    undo_count = steps;
                 }
             }
+            if self.history.undo_list().is_empty() && self.history.redo_list().is_empty() {
+                ui.label("No edits yet");
+            }
+        });
+        for _ in 0..undo_count {
+            self.undo();
+        }
+        for _ in 0..redo_count {
+            self.redo();
+        }
+        if undo_count > 0 || redo_count > 0 {
+            ui.close_menu();
         }
     }
 
     pub fn reset(&mut self)
     {
         self.path = Default::default();
+        self.pac = None;
         self.jonbins = Default::default();
+        self.modified = Default::default();
+        self.unsaved = Default::default();
+        self.parse_failures = Default::default();
         self.selected = "".to_string();
-        self.offset_x = 480.0;
-        self.offset_y = 802.0;
+        self.view = Default::default();
         self.last_cursor_pos = Default::default();
+        self.selected_box = None;
+        self.drag_mode = None;
+        self.drag_start = None;
         self.box_info = Default::default();
+        self.box_info_source = None;
+        self.box_info_errors = Default::default();
         self.current_name = Default::default();
         self.char_script = Default::default();
         self.ef_script = Default::default();
         self.states = Default::default();
         self.ef_states = Default::default();
+        self.char_script_errors = Default::default();
+        self.ef_script_errors = Default::default();
         self.current_state = Default::default();
+        self.playback = Default::default();
         self.show_state_list = true;
         self.show_state = true;
         self.box_changed = true;
+        self.history = Default::default();
+        self.textures = Default::default();
     }
 
-    pub fn open_file(&mut self, pac: &GGSTPac) -> bool {
-        self.read_pac(pac);
+    pub fn open_file(&mut self, pac: &GGSTPac, offsets: &[u64]) -> bool {
+        self.read_pac(pac, offsets);
+        self.pac = Some(pac.clone());
         return true;
     }
 
-    fn read_pac(&mut self, pac: &GGSTPac) {
-        for i in &pac.files {
-            match GGSTJonBin::parse(&i.contents, self.is_gbvs){
+    /// Serializes the loaded PAC, replacing the contents of every jonbin that has been edited.
+    pub fn repack(&self) -> Option<Vec<u8>> {
+        let mut pac = self.pac.clone()?;
+        for file in &mut pac.files {
+            if self.modified.contains(&file.name) {
+                if let Some(jonb) = self.jonbins.get(&file.name) {
+                    file.contents = jonb.to_bytes();
+                }
+            }
+        }
+        Some(pac.to_bytes())
+    }
+
+    fn read_pac(&mut self, pac: &GGSTPac, offsets: &[u64]) {
+        for (index, i) in pac.files.iter().enumerate() {
+            match self.game.parse_jonbin(&i.contents){
                 Ok(jonb) => {
                     self.jonbins.insert(i.name.clone(),
                 jonb);
                 },
                 Err(e) => {
-                    println!("Error reading file {}: {}", i.name, e);
+                    self.parse_failures.push(ParseFailure {
+                        name: i.name.clone(),
+                        offset: offsets.get(index).copied(),
+                        error: e,
+                    });
                     continue},
             };
         }
     }
 
+    /// Drops the parsed character script so it is parsed again from the current script bytes.
+    pub fn clear_char_script(&mut self) {
+        self.char_script = Default::default();
+        self.char_script_errors = Default::default();
+        self.states = Default::default();
+        self.current_state = Default::default();
+    }
+
+    /// Drops the parsed effect script so it is parsed again from the current script bytes.
+    pub fn clear_ef_script(&mut self) {
+        self.ef_script = Default::default();
+        self.ef_script_errors = Default::default();
+        self.ef_states = Default::default();
+        self.current_state = Default::default();
+    }
+
     pub fn collect_states(&mut self) {
-        let begin_state: Vec<_> = self.char_script.match_indices("beginState").collect();
-        let end_state: Vec<_> = self.char_script.match_indices("endState").collect();
-        
-        for (index, state_pos) in begin_state.iter().enumerate() {
-            let state = self.char_script.substring(state_pos.0, end_state[index].0).to_string();
-            let state_end = self.char_script[state_pos.0..].find(0xa as char).map(|i| i + state_pos.0);
-            let state_name = self.char_script.substring(state_pos.0, state_end.unwrap()).to_string();
-            self.states.insert(state_name, state);
-        }
+        let script = states::parse(&self.char_script);
+        self.char_script_errors = script.errors;
+        self.states = script.states.into_iter().map(|state| (state.name.clone(), state)).collect();
     }
 
     pub fn collect_ef_states(&mut self) {
-        let begin_state: Vec<_> = self.ef_script.match_indices("beginState").collect();
-        let end_state: Vec<_> = self.ef_script.match_indices("endState").collect();
-        
-        for (index, state_pos) in begin_state.iter().enumerate() {
-            let state = self.ef_script.substring(state_pos.0, end_state[index].0).to_string();
-            let state_end = self.ef_script[state_pos.0..].find(0xa as char).map(|i| i + state_pos.0);
-            let state_name = self.ef_script.substring(state_pos.0, state_end.unwrap()).to_string();
-            self.ef_states.insert(state_name, state);
-        }
+        let script = states::parse(&self.ef_script);
+        self.ef_script_errors = script.errors;
+        self.ef_states = script.states.into_iter().map(|state| (state.name.clone(), state)).collect();
     }
 
     fn display_state(&mut self, ui: &mut egui::Ui)
     {
+        // Taken out for the duration of the frame so the sprite lines can update the selection.
+        let state = match self.current_state.take() {
+            Some(state) => state,
+            None => return,
+        };
+        ui.strong(&state.name);
+        ui.label(self.frame_data(&state).summary()).on_hover_text(framedata::METHOD);
+        let total_frames = state.total_frames();
+        if total_frames > 0 {
+            self.timeline(ui, &state, total_frames);
+        }
+        let current_sprite = state.sprite_at(self.playback.frame).map(|sprite| sprite.instruction);
         egui::ScrollArea::vertical()
         .max_width(250.0)
         .show(ui, |ui| {
-            let line_breaks: Vec<_> = self.current_state.1.match_indices(0xa as char).collect();
-            let mut prev_index: usize = 0;
-    
-            for line_index in line_breaks {
-                let line = self.current_state.1.substring(prev_index, line_index.0).to_string();
-                if line.contains("sprite: ") {
-                    if ui.selectable_label(true, &line)
-                    .clicked()
-                    {
-                        let quotes: Vec<_> = line.match_indices("'").collect();
-                        let name = line.substring(quotes[0].0 + 1, quotes[1].0).to_string();
-                        self.selected = name.to_string();
-                        self.current_name = "".to_string();
-                        self.reset_image = true;
-                        self.image = None;
-                    };
-                }
-                else if line.contains("hit:")
-                {
-                    ui.colored_label(egui::Color32::RED, &line);
-                }
-                else if line.contains("grabOrRelease:")
-                {
-                    ui.colored_label(egui::Color32::RED, &line);
-                }
-                else {
-                    ui.label(&line);
+            for (index, instruction) in state.instructions.iter().enumerate() {
+                let line = format!("{}{}", " ".repeat(instruction.indent), instruction.text);
+                match instruction.name.as_str() {
+                    "sprite" => {
+                        let name = instruction.arg_str(0).unwrap_or_default().to_string();
+                        let text = if self.failed_to_parse(&name) {
+                            egui::RichText::new(format!("⚠ {} (failed to parse)", line)).color(Color32::RED)
+                        }
+                        else {
+                            egui::RichText::new(&line)
+                        };
+                        if ui.selectable_label(current_sprite == Some(index), text)
+                        .clicked()
+                        {
+                            self.playback.playing = false;
+                            if let Some(frame) = state.start_frame(index) {
+                                self.playback.frame = frame;
+                            }
+                            self.select_sprite(name);
+                        };
+                    }
+                    "hit" | "grabOrRelease" => {
+                        ui.colored_label(egui::Color32::RED, &line);
+                    }
+                    "label" => {
+                        ui.colored_label(egui::Color32::LIGHT_BLUE, &line);
+                    }
+                    _ => {
+                        ui.label(&line);
+                    }
                 }
-                prev_index = line_index.0 + 1;
             }
         });
+        self.current_state = Some(state);
+    }
+
+    /// Play/pause/step controls and a frame scrubber for `state`, keeping the canvas on the
+    /// sprite that is shown on the current frame.
+    fn timeline(&mut self, ui: &mut egui::Ui, state: &State, total_frames: u32) {
+        let mut frame = self.playback.frame.min(total_frames - 1);
+        ui.horizontal(|ui| {
+            if ui.button("⏮").on_hover_text("First frame").clicked() {
+                self.playback.playing = false;
+                frame = 0;
+            }
+            if ui.button("◀").on_hover_text("Previous frame").clicked() {
+                self.playback.playing = false;
+                frame = frame.saturating_sub(1);
+            }
+            let (icon, hint) = if self.playback.playing { ("⏸", "Pause") } else { ("▶", "Play") };
+            if ui.button(icon).on_hover_text(hint).clicked() {
+                self.playback.playing = !self.playback.playing;
+                self.playback.elapsed = 0.0;
+            }
+            if ui.button("▶|").on_hover_text("Next frame").clicked() {
+                self.playback.playing = false;
+                frame = (frame + 1).min(total_frames - 1);
+            }
+        });
+        let mut shown_frame = frame + 1;
+        if ui.add(egui::Slider::new(&mut shown_frame, 1..=total_frames).text("frame")).changed() {
+            self.playback.playing = false;
+            frame = shown_frame - 1;
+        }
+        if self.playback.playing {
+            self.playback.elapsed += ui.input().unstable_dt as f64;
+            let steps = (self.playback.elapsed * GAME_FPS) as u32;
+            if steps > 0 {
+                self.playback.elapsed -= steps as f64 / GAME_FPS;
+                frame = (frame + steps) % total_frames;
+            }
+            ui.ctx().request_repaint();
+        }
+
+        let frame_changed = frame != self.playback.frame;
+        self.playback.frame = frame;
+        if let Some(sprite) = state.sprite_at(frame) {
+            ui.label(format!("Frame {} / {}: {}", frame + 1, total_frames, sprite.name));
+            if (frame_changed || self.selected.is_empty()) && self.selected != sprite.name {
+                self.select_sprite(sprite.name);
+            }
+        }
+    }
+
+    /// Frame data of `state`, going by which of its sprites have hitboxes.
+    pub fn frame_data(&self, state: &State) -> FrameData {
+        framedata::compute(state, |sprite| framedata::has_hitbox(&self.jonbins, sprite))
+    }
+
+    /// The selected sprite, if it has been loaded from the PAC.
+    pub fn selected_sprite(&self) -> Option<&str> {
+        self.jonbins.contains_key(&self.selected).then(|| self.selected.as_str())
+    }
+
+    /// Exports `sprite`, or every jonbin if `None`, as a JSON document.
+    pub fn export_json(&self, sprite: Option<&str>) -> Result<Vec<u8>, String> {
+        let document = match sprite {
+            Some(sprite) => match self.jonbins.get(sprite) {
+                Some(jonb) => Document::jonbin(sprite, jonb),
+                None => return Err(format!("{} is not in the loaded pac", sprite)),
+            },
+            None => Document::pac(&self.jonbins),
+        };
+        document.to_bytes()
+    }
+
+    /// Replaces the boxes of the jonbins in a JSON document with the ones it describes. Nothing
+    /// is changed unless every jonbin is in the loaded PAC. Each changed sprite gets its own
+    /// undo step. Returns how many sprites changed.
+    pub fn import_json(&mut self, bytes: &[u8]) -> Result<usize, String> {
+        let document = Document::parse(bytes)?;
+        if let Some(missing) = document.jonbins().iter().find(|jonbin| !self.jonbins.contains_key(&jonbin.name)) {
+            return Err(format!("{} is not in the loaded pac", missing.name));
+        }
+        // Work out every jonbin's boxes first, so a bad group doesn't leave half the file imported.
+        let mut imported = vec![];
+        for jonbin in document.jonbins() {
+            if let Some(jonb) = self.jonbins.get(&jonbin.name) {
+                imported.push((jonbin, jonbin.apply(&jonb.boxes, self.game)?));
+            }
+        }
+        let mut changed = 0;
+        for (jonbin, after) in imported {
+            let jonb = match self.jonbins.get_mut(&jonbin.name) {
+                Some(jonb) => jonb,
+                None => continue,
+            };
+            let before = jonb.boxes.clone();
+            if boxes_eq(&before, &after) {
+                continue;
+            }
+            jonb.boxes = after.clone();
+            if jonbin.name == self.selected {
+                self.selected_box = None;
+            }
+            self.history.push(Edit {
+                sprite: jonbin.name.clone(),
+                description: "Import JSON".to_string(),
+                before,
+                after,
+                selected_before: None,
+                selected_after: None,
+                mergeable: false,
+            });
+            self.modified.insert(jonbin.name.clone());
+            self.unsaved.insert(jonbin.name.clone());
+            changed += 1;
+        }
+        Ok(changed)
+    }
+
+    pub fn has_states(&self) -> bool {
+        !self.states.is_empty()
+    }
+
+    /// The frame data of every state in the character script, in state name order.
+    pub fn frame_table(&self) -> Vec<Row> {
+        framedata::table(self.states.values(), &self.jonbins)
+    }
+
+    fn select_sprite(&mut self, name: String) {
+        self.selected_box = None;
+        self.current_name = "".to_string();
+        match self.textures.get(&name) {
+            // The image download is left alone, as there is nothing to fetch.
+            Some(texture) => self.texture = Some(texture.clone()),
+            None => {
+                self.reset_image = true;
+                self.image = None;
+            }
+        }
+        self.selected = name;
+    }
+
+    /// Sprites of the playing state whose renders haven't been loaded yet, so they can be
+    /// fetched before playback gets to them.
+    pub fn sprites_to_prefetch(&self) -> BTreeSet<String> {
+        match &self.current_state {
+            Some(state) if self.playback.playing => state
+                .sprites()
+                .into_iter()
+                .map(|sprite| sprite.name)
+                .filter(|name| !self.textures.contains_key(name))
+                .collect(),
+            _ => BTreeSet::new(),
+        }
     }
 
     pub fn bytes_to_image(&mut self, bytes: &Vec<u8>) -> Result<egui::ColorImage, ImageError>{
@@ -421,4 +1102,180 @@ Double click to reset to the original position.");
         };
         image
     }
-}
\ No newline at end of file
+}
+
+/// Returns the on-screen rectangle of `hitbox`, normalized so that `min` is the top-left corner
+/// even when the box is stored with a negative width or height.
+fn box_screen_rect(hitbox: &HitBox, view: &Transform) -> Rect {
+    view.rect_to_screen(box_world_rect(hitbox))
+}
+
+/// The rectangle `hitbox` covers in game units, normalized like `box_screen_rect`.
+fn box_world_rect(hitbox: &HitBox) -> Rect {
+    Rect::from_two_pos(
+        Pos2{x: hitbox.rect.x_offset, y: hitbox.rect.y_offset},
+        Pos2{x: hitbox.rect.x_offset + hitbox.rect.width, y: hitbox.rect.y_offset + hitbox.rect.height},
+    )
+}
+
+/// Compares box groups field by field, since a drag that ends where it started isn't an edit.
+fn boxes_eq(a: &[Vec<HitBox>], b: &[Vec<HitBox>]) -> bool {
+    a.len() == b.len() && a.iter().zip(b).all(|(a, b)| {
+        a.len() == b.len() && a.iter().zip(b).all(|(a, b)| {
+            a.kind == b.kind
+                && a.rect.x_offset == b.rect.x_offset
+                && a.rect.y_offset == b.rect.y_offset
+                && a.rect.width == b.rect.width
+                && a.rect.height == b.rect.height
+        })
+    })
+}
+
+/// Works out what dragging from `pos` would do to a box drawn at `rect`, if anything.
+fn drag_mode_at(rect: Rect, pos: Pos2) -> Option<DragMode> {
+    if !rect.expand(HANDLE_SIZE).contains(pos) {
+        return None;
+    }
+    let left = (pos.x - rect.min.x).abs() <= HANDLE_SIZE;
+    let right = !left && (pos.x - rect.max.x).abs() <= HANDLE_SIZE;
+    let top = (pos.y - rect.min.y).abs() <= HANDLE_SIZE;
+    let bottom = !top && (pos.y - rect.max.y).abs() <= HANDLE_SIZE;
+    if left || right || top || bottom {
+        Some(DragMode::Resize { left, right, top, bottom })
+    }
+    else {
+        Some(DragMode::Move)
+    }
+}
+
+fn box_shape(hitbox: &HitBox) -> BoxShape {
+    BoxType::try_from(hitbox.kind).map_or(BoxShape::Rect, BoxType::shape)
+}
+
+/// Where a point is, or where a vector starts, in game units.
+fn box_start(hitbox: &HitBox) -> Pos2 {
+    Pos2{x: hitbox.rect.x_offset, y: hitbox.rect.y_offset}
+}
+
+/// Where a vector points to, in game units.
+fn box_tip(hitbox: &HitBox) -> Pos2 {
+    box_start(hitbox) + Vec2{x: hitbox.rect.width, y: hitbox.rect.height}
+}
+
+/// Works out what dragging from `pos` would do to `hitbox`, going by how its kind is drawn.
+fn box_drag_mode(hitbox: &HitBox, view: &Transform, pos: Pos2) -> Option<DragMode> {
+    let near = |point: Pos2| (view.to_screen(point) - pos).length() <= CROSSHAIR_SIZE;
+    match box_shape(hitbox) {
+        BoxShape::Rect => drag_mode_at(box_screen_rect(hitbox, view), pos),
+        BoxShape::Point => near(box_start(hitbox)).then(|| DragMode::Move),
+        // The tip wins over the start, so a vector of length zero can still be pulled out.
+        BoxShape::Vector if near(box_tip(hitbox)) => Some(DragMode::Tip),
+        BoxShape::Vector => near(box_start(hitbox)).then(|| DragMode::Move),
+    }
+}
+
+/// Finds the box under `pos`, preferring the currently selected box and then the topmost one.
+/// Boxes whose kind isn't `visible` are skipped.
+fn box_at(boxes: &[Vec<HitBox>], selected: Option<(usize, usize)>, pos: Pos2, view: &Transform, visible: impl Fn(u32) -> bool) -> Option<((usize, usize), DragMode)> {
+    if let Some((group, index)) = selected {
+        if let Some(hitbox) = boxes.get(group).and_then(|g| g.get(index)).filter(|hitbox| visible(hitbox.kind)) {
+            if let Some(mode) = box_drag_mode(hitbox, view, pos) {
+                return Some(((group, index), mode));
+            }
+        }
+    }
+    boxes_at(boxes, pos, view, visible).into_iter().next()
+}
+
+/// Every box under `pos`, topmost first. Boxes whose kind isn't `visible` are skipped.
+fn boxes_at(boxes: &[Vec<HitBox>], pos: Pos2, view: &Transform, visible: impl Fn(u32) -> bool) -> Vec<((usize, usize), DragMode)> {
+    let mut hits = vec![];
+    for (group, boxgroup) in boxes.iter().enumerate().rev() {
+        for (index, hitbox) in boxgroup.iter().enumerate().rev().filter(|(_, hitbox)| visible(hitbox.kind)) {
+            if let Some(mode) = box_drag_mode(hitbox, view, pos) {
+                hits.push(((group, index), mode));
+            }
+        }
+    }
+    hits
+}
+
+/// Applies a pointer movement of `delta` game units to `hitbox`.
+fn drag_box(hitbox: &mut HitBox, mode: DragMode, delta: Vec2) {
+    let rect = &mut hitbox.rect;
+    match mode {
+        DragMode::Move => {
+            rect.x_offset += delta.x;
+            rect.y_offset += delta.y;
+        }
+        DragMode::Tip => {
+            rect.width += delta.x;
+            rect.height += delta.y;
+        }
+        DragMode::Resize { left, right, top, bottom } => {
+            // Edges are picked on the normalized screen rect, so for a box with a negative
+            // size the left/top edge is `offset + size` rather than `offset`.
+            if left || right {
+                if (rect.width >= 0.0) == left {
+                    rect.x_offset += delta.x;
+                    rect.width -= delta.x;
+                }
+                else {
+                    rect.width += delta.x;
+                }
+            }
+            if top || bottom {
+                if (rect.height >= 0.0) == top {
+                    rect.y_offset += delta.y;
+                    rect.height -= delta.y;
+                }
+                else {
+                    rect.height += delta.y;
+                }
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn hitbox(x_offset: f32, y_offset: f32, width: f32, height: f32) -> HitBox {
+        HitBox { kind: BoxType::Hurtbox as u32, rect: JonbinRect { x_offset, y_offset, width, height } }
+    }
+
+    fn resize(left: bool, right: bool, top: bool, bottom: bool) -> DragMode {
+        DragMode::Resize { left, right, top, bottom }
+    }
+
+    #[test]
+    fn drag_box_moves_the_edge_that_was_grabbed() {
+        let delta = Vec2 { x: 10.0, y: 5.0 };
+        // Each case is the box before, the drag, and the area it should cover afterwards as
+        // [left, top, right, bottom].
+        let cases = [
+            ("move", hitbox(0.0, 0.0, 50.0, 40.0), DragMode::Move, [10.0, 5.0, 60.0, 45.0]),
+            ("right edge", hitbox(0.0, 0.0, 50.0, 40.0), resize(false, true, false, false), [0.0, 0.0, 60.0, 40.0]),
+            ("left edge", hitbox(0.0, 0.0, 50.0, 40.0), resize(true, false, false, false), [10.0, 0.0, 50.0, 40.0]),
+            ("top left corner", hitbox(0.0, 0.0, 50.0, 40.0), resize(true, false, true, false), [10.0, 5.0, 50.0, 40.0]),
+            ("left edge, negative width", hitbox(100.0, 0.0, -50.0, 40.0), resize(true, false, false, false), [60.0, 0.0, 100.0, 40.0]),
+            ("right edge, negative width", hitbox(100.0, 0.0, -50.0, 40.0), resize(false, true, false, false), [50.0, 0.0, 110.0, 40.0]),
+            ("top edge, negative height", hitbox(0.0, 40.0, 50.0, -40.0), resize(false, false, true, false), [0.0, 5.0, 50.0, 40.0]),
+            ("bottom edge, negative height", hitbox(0.0, 40.0, 50.0, -40.0), resize(false, false, false, true), [0.0, 0.0, 50.0, 45.0]),
+        ];
+        for (name, mut hitbox, mode, [left, top, right, bottom]) in cases {
+            drag_box(&mut hitbox, mode, delta);
+            let rect = box_world_rect(&hitbox);
+            assert_eq!([rect.left(), rect.top(), rect.right(), rect.bottom()], [left, top, right, bottom], "{}", name);
+        }
+    }
+
+    #[test]
+    fn drag_box_moves_the_tip_of_a_vector() {
+        let mut vector = hitbox(10.0, 20.0, 30.0, -15.0);
+        drag_box(&mut vector, DragMode::Tip, Vec2 { x: -5.0, y: 5.0 });
+        assert_eq!(box_start(&vector), Pos2 { x: 10.0, y: 20.0 });
+        assert_eq!(box_tip(&vector), Pos2 { x: 35.0, y: 10.0 });
+    }
+}
@@ -0,0 +1,31 @@
+use std::fmt;
+
+/// Why one of the editor's inputs could not be loaded.
+#[derive(Clone, Debug)]
+pub enum LoadError {
+    /// The server answered, but not with the file.
+    Http { url: String, status: u16, status_text: String },
+    /// The request never got an answer, e.g. because the browser is offline.
+    Network(String),
+    /// A file from a local directory couldn't be read.
+    Io(String),
+    /// The sprite image isn't a format the `image` crate can decode.
+    Decode(String),
+    /// bbscript couldn't load the command DB or parse a script.
+    Script(String),
+    /// arcsys couldn't parse the collision PAC.
+    Pac(String),
+}
+
+impl fmt::Display for LoadError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            LoadError::Http { url, status, status_text } => write!(f, "{} returned HTTP {} {}", url, status, status_text),
+            LoadError::Network(e) => write!(f, "Network error: {}", e),
+            LoadError::Io(e) => write!(f, "{}", e),
+            LoadError::Decode(e) => write!(f, "Could not decode image: {}", e),
+            LoadError::Script(e) => write!(f, "Script error: {}", e),
+            LoadError::Pac(e) => write!(f, "Could not parse pac: {}", e),
+        }
+    }
+}
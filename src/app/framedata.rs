@@ -0,0 +1,261 @@
+use std::collections::{BTreeMap, BTreeSet};
+use arcsys::ggst::jonbin::{GGSTJonBin, HitBox};
+use serde::Serialize;
+use super::game::BoxType;
+use super::states::State;
+
+/// Instructions after which the move counts as recovered, even if the state keeps showing
+/// sprites (e.g. a hold at the end that the next state cancels out of).
+const RECOVERY_MARKERS: [&str; 1] = ["recoveryState"];
+
+/// How `compute` gets its numbers, shown next to them in the state panel.
+pub const METHOD: &str = "Active frames are the ones showing a sprite with hitboxes. Commands such as `hit` \
+    aren't read, so moves whose hits are spawned by the script rather than drawn in the sprite's \
+    boxes show up without active frames. Recovery ends at recoveryState, or else after the last sprite.";
+
+/// Frame data of a move in the notation frame data wikis use: startup counts up to and
+/// including the first active frame.
+#[derive(Clone, Copy, PartialEq, Debug, Default)]
+pub struct FrameData {
+    /// `None` for states without any hitbox.
+    pub startup: Option<u32>,
+    /// From the first to the last frame with a hitbox, so gaps between hits are included.
+    pub active: Option<u32>,
+    pub recovery: Option<u32>,
+    /// Frames until the move ends: the recovery marker if there is one after the active
+    /// frames, otherwise the end of the last sprite.
+    pub total: u32,
+}
+
+/// Works out the frame data of `state` from its sprite durations. `has_hitbox` tells whether
+/// a sprite has any hitboxes; frames showing such a sprite are active.
+pub fn compute(state: &State, has_hitbox: impl Fn(&str) -> bool) -> FrameData {
+    let mut frame = 0;
+    let mut active: Option<(u32, u32)> = None;
+    let mut recovered_at = None;
+    for instruction in &state.instructions {
+        if instruction.name == "sprite" {
+            let name = instruction.arg_str(0).unwrap_or_default();
+            let duration = instruction.arg_int(1).unwrap_or(0).max(0) as u32;
+            if duration > 0 && has_hitbox(name) {
+                let first = active.map_or(frame, |(first, _)| first);
+                active = Some((first, frame + duration));
+                recovered_at = None;
+            }
+            frame += duration;
+        }
+        else if RECOVERY_MARKERS.contains(&instruction.name.as_str()) && active.is_some() && recovered_at.is_none() {
+            recovered_at = Some(frame);
+        }
+    }
+
+    match active {
+        Some((first, last)) => {
+            let end = recovered_at.unwrap_or(frame).max(last);
+            FrameData {
+                startup: Some(first + 1),
+                active: Some(last - first),
+                recovery: Some(end - last),
+                total: end,
+            }
+        }
+        None => FrameData { total: frame, ..Default::default() },
+    }
+}
+
+impl FrameData {
+    /// One line for the state panel, with `-` for the numbers a move without hitboxes lacks.
+    pub fn summary(&self) -> String {
+        let show = |value: Option<u32>| value.map_or("-".to_string(), |value| value.to_string());
+        format!(
+            "Startup {}  Active {}  Recovery {}  Total {}",
+            show(self.startup),
+            show(self.active),
+            show(self.recovery),
+            self.total
+        )
+    }
+}
+
+/// Whether the jonbin of `sprite` has any hitboxes.
+pub fn has_hitbox(jonbins: &BTreeMap<String, GGSTJonBin>, sprite: &str) -> bool {
+    jonbins
+        .get(sprite)
+        .and_then(|jonb| jonb.boxes.get(BoxType::Hitbox as usize))
+        .map_or(false, |group| !group.is_empty())
+}
+
+/// The frame data of each of `states`, with the hitboxes of their sprites looked up in `jonbins`.
+pub fn table<'a>(states: impl IntoIterator<Item = &'a State>, jonbins: &BTreeMap<String, GGSTJonBin>) -> Vec<Row> {
+    states
+        .into_iter()
+        .map(|state| {
+            let sprites: BTreeSet<String> = state.sprites().into_iter().map(|sprite| sprite.name).collect();
+            let hitboxes: Vec<&HitBox> = sprites
+                .iter()
+                .filter_map(|sprite| jonbins.get(sprite))
+                .filter_map(|jonb| jonb.boxes.get(BoxType::Hitbox as usize))
+                .flatten()
+                .collect();
+            let max_reach = hitboxes
+                .iter()
+                .map(|hitbox| {
+                    // Boxes with a negative width extend left of their offset.
+                    hitbox.rect.x_offset.max(hitbox.rect.x_offset + hitbox.rect.width)
+                })
+                .reduce(f32::max);
+            let frame_data = compute(state, |sprite| has_hitbox(jonbins, sprite));
+            Row::new(&state.name, frame_data, hitboxes.len(), max_reach)
+        })
+        .collect()
+}
+
+/// A move's line in an exported frame data table.
+#[derive(Clone, PartialEq, Debug, Serialize)]
+pub struct Row {
+    #[serde(rename = "move")]
+    pub name: String,
+    pub startup: Option<u32>,
+    pub active: Option<u32>,
+    pub recovery: Option<u32>,
+    pub total: u32,
+    /// Hitboxes across the distinct sprites the state shows.
+    pub hitboxes: usize,
+    /// Furthest right edge of any of those hitboxes, relative to the character's origin.
+    pub max_reach: Option<f32>,
+}
+
+impl Row {
+    pub fn new(name: &str, frame_data: FrameData, hitboxes: usize, max_reach: Option<f32>) -> Row {
+        Row {
+            name: name.to_string(),
+            startup: frame_data.startup,
+            active: frame_data.active,
+            recovery: frame_data.recovery,
+            total: frame_data.total,
+            hitboxes,
+            max_reach,
+        }
+    }
+}
+
+/// The formats a frame data table can be exported as.
+#[derive(Copy, Clone, PartialEq, Debug)]
+pub enum TableFormat {
+    Csv,
+    Json,
+}
+
+impl TableFormat {
+    pub fn extension(self) -> &'static str {
+        match self {
+            TableFormat::Csv => "csv",
+            TableFormat::Json => "json",
+        }
+    }
+
+    pub fn write(self, rows: &[Row]) -> Result<Vec<u8>, String> {
+        match self {
+            TableFormat::Csv => Ok(to_csv(rows).into_bytes()),
+            TableFormat::Json => serde_json::to_vec_pretty(rows).map_err(|e| e.to_string()),
+        }
+    }
+}
+
+/// Renders the table as CSV with a header line. Empty cells stand for numbers a move without
+/// hitboxes doesn't have.
+pub fn to_csv(rows: &[Row]) -> String {
+    let cell = |value: Option<u32>| value.map_or(String::new(), |value| value.to_string());
+    let mut csv = "move,startup,active,recovery,total,hitboxes,max_reach\n".to_string();
+    for row in rows {
+        let name = if row.name.contains(|c| c == ',' || c == '"' || c == '\n') {
+            format!("\"{}\"", row.name.replace('"', "\"\""))
+        } else {
+            row.name.clone()
+        };
+        csv += &format!(
+            "{},{},{},{},{},{},{}\n",
+            name,
+            cell(row.startup),
+            cell(row.active),
+            cell(row.recovery),
+            row.total,
+            row.hitboxes,
+            row.max_reach.map_or(String::new(), |reach| reach.to_string())
+        );
+    }
+    csv
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::app::states;
+
+    fn state(instructions: &[&str]) -> State {
+        let script = format!("beginState: s32'Test'\n{}\nendState\n", instructions.join("\n"));
+        states::parse(&script).states.remove(0)
+    }
+
+    fn frame_data(startup: u32, active: u32, recovery: u32, total: u32) -> FrameData {
+        FrameData { startup: Some(startup), active: Some(active), recovery: Some(recovery), total }
+    }
+
+    #[test]
+    fn compute_counts_startup_active_and_recovery() {
+        let cases: [(&str, &[&str], FrameData); 6] = [
+            (
+                "no hitboxes",
+                &["sprite: s32'idle', 3", "sprite: s32'idle', 4"],
+                FrameData { total: 7, ..Default::default() },
+            ),
+            (
+                "single hit",
+                &["sprite: s32'idle', 3", "sprite: s32'hit', 2", "sprite: s32'idle', 4"],
+                frame_data(4, 2, 4, 9),
+            ),
+            (
+                "gap between hits counts as active",
+                &["sprite: s32'idle', 2", "sprite: s32'hit', 1", "sprite: s32'idle', 2", "sprite: s32'hit', 1", "sprite: s32'idle', 3"],
+                frame_data(3, 4, 3, 9),
+            ),
+            (
+                "recoveryState ends the move",
+                &["sprite: s32'idle', 2", "sprite: s32'hit', 2", "sprite: s32'idle', 3", "recoveryState", "sprite: s32'idle', 10"],
+                frame_data(3, 2, 3, 7),
+            ),
+            (
+                "recoveryState before the active frames is ignored",
+                &["recoveryState", "sprite: s32'idle', 2", "sprite: s32'hit', 1", "sprite: s32'idle', 2"],
+                frame_data(3, 1, 2, 5),
+            ),
+            (
+                "zero length sprites aren't active",
+                &["sprite: s32'idle', 2", "sprite: s32'hit', 0", "sprite: s32'idle', 2"],
+                FrameData { total: 4, ..Default::default() },
+            ),
+        ];
+        for (name, instructions, expected) in cases {
+            assert_eq!(compute(&state(instructions), |sprite| sprite == "hit"), expected, "{}", name);
+        }
+    }
+
+    #[test]
+    fn to_csv_quotes_names_that_need_it() {
+        let row = |name: &str, max_reach| Row::new(name, frame_data(4, 2, 10, 15), 1, max_reach);
+        let csv = to_csv(&[row("5K", Some(120.5)), row("j.D, air", None), row("Say \"hi\"", None)]);
+        assert_eq!(
+            csv,
+            "move,startup,active,recovery,total,hitboxes,max_reach\n\
+             5K,4,2,10,15,1,120.5\n\
+             \"j.D, air\",4,2,10,15,1,\n\
+             \"Say \"\"hi\"\"\",4,2,10,15,1,\n"
+        );
+    }
+
+    #[test]
+    fn to_csv_leaves_missing_numbers_empty() {
+        let csv = to_csv(&[Row::new("Idle", FrameData { total: 30, ..Default::default() }, 0, None)]);
+        assert_eq!(csv.lines().nth(1), Some("Idle,,,,30,0,"));
+    }
+}
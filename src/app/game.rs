@@ -0,0 +1,304 @@
+use arcsys::ggst::{jonbin::GGSTJonBin, pac::GGSTPac};
+use eframe::epaint::Color32;
+use serde::{Deserialize, Serialize};
+
+#[derive(Copy, Clone, PartialEq, Debug, Serialize, Deserialize)]
+pub enum BoxType {
+    Hurtbox = 0,
+    Hitbox = 1,
+    ExPoint = 2,
+    ExRect = 3,
+    ExVector = 4,
+    Push = 5,
+    TempCenter = 6,
+    Neck = 7,
+    Abdominal = 8,
+    AttackVsPush = 9,
+    SpGuard = 10,
+    RLeg = 11,
+    LLeg = 12,
+    Private0 = 13,
+    Private1 = 14,
+    Private2 = 15,
+    Private3 = 16,
+    ExtendJon = 17,
+}
+
+impl TryFrom<u32> for BoxType {
+    type Error = ();
+
+    fn try_from(v: u32) -> Result<Self, Self::Error> {
+        match v {
+            x if x == BoxType::Hurtbox as u32 => Ok(BoxType::Hurtbox),
+            x if x == BoxType::Hitbox as u32 => Ok(BoxType::Hitbox),
+            x if x == BoxType::ExPoint as u32 => Ok(BoxType::ExPoint),
+            x if x == BoxType::ExRect as u32 => Ok(BoxType::ExRect),
+            x if x == BoxType::ExVector as u32 => Ok(BoxType::ExVector),
+            x if x == BoxType::Push as u32 => Ok(BoxType::Push),
+            x if x == BoxType::TempCenter as u32 => Ok(BoxType::TempCenter),
+            x if x == BoxType::Neck as u32 => Ok(BoxType::Neck),
+            x if x == BoxType::Abdominal as u32 => Ok(BoxType::Abdominal),
+            x if x == BoxType::AttackVsPush as u32 => Ok(BoxType::AttackVsPush),
+            x if x == BoxType::SpGuard as u32 => Ok(BoxType::SpGuard),
+            x if x == BoxType::RLeg as u32 => Ok(BoxType::RLeg),
+            x if x == BoxType::LLeg as u32 => Ok(BoxType::LLeg),
+            x if x == BoxType::Private0 as u32 => Ok(BoxType::Private0),
+            x if x == BoxType::Private1 as u32 => Ok(BoxType::Private1),
+            x if x == BoxType::Private2 as u32 => Ok(BoxType::Private2),
+            x if x == BoxType::Private3 as u32 => Ok(BoxType::Private3),
+            x if x == BoxType::ExtendJon as u32 => Ok(BoxType::ExtendJon),
+            _ => Err(()),
+        }
+    }
+}
+
+/// What the four numbers of a box actually describe.
+#[derive(Copy, Clone, PartialEq, Debug)]
+pub enum BoxShape {
+    Rect,
+    /// Only `x_offset`/`y_offset` are meaningful.
+    Point,
+    /// A direction of `width`/`height` starting at `x_offset`/`y_offset`.
+    Vector,
+}
+
+impl Default for BoxType {
+    fn default() -> Self {
+        BoxType::Hurtbox
+    }
+}
+
+impl BoxType {
+    pub const ALL: [BoxType; 18] = [
+        BoxType::Hurtbox,
+        BoxType::Hitbox,
+        BoxType::ExPoint,
+        BoxType::ExRect,
+        BoxType::ExVector,
+        BoxType::Push,
+        BoxType::TempCenter,
+        BoxType::Neck,
+        BoxType::Abdominal,
+        BoxType::AttackVsPush,
+        BoxType::SpGuard,
+        BoxType::RLeg,
+        BoxType::LLeg,
+        BoxType::Private0,
+        BoxType::Private1,
+        BoxType::Private2,
+        BoxType::Private3,
+        BoxType::ExtendJon,
+    ];
+
+    pub fn shape(self) -> BoxShape {
+        match self {
+            BoxType::ExPoint | BoxType::TempCenter => BoxShape::Point,
+            BoxType::ExVector => BoxShape::Vector,
+            _ => BoxShape::Rect,
+        }
+    }
+
+    pub fn name(self) -> &'static str {
+        match self {
+            BoxType::Hurtbox => "Hurtbox",
+            BoxType::Hitbox => "Hitbox",
+            BoxType::ExPoint => "ExPoint",
+            BoxType::ExRect => "ExRect",
+            BoxType::ExVector => "ExVector",
+            BoxType::Push => "Push",
+            BoxType::TempCenter => "TempCenter",
+            BoxType::Neck => "Neck",
+            BoxType::Abdominal => "Abdominal",
+            BoxType::AttackVsPush => "AttackVsPush",
+            BoxType::SpGuard => "SpGuard",
+            BoxType::RLeg => "RLeg",
+            BoxType::LLeg => "LLeg",
+            BoxType::Private0 => "Private0",
+            BoxType::Private1 => "Private1",
+            BoxType::Private2 => "Private2",
+            BoxType::Private3 => "Private3",
+            BoxType::ExtendJon => "ExtendJon",
+        }
+    }
+}
+
+/// Box kinds of the jonbins before Strive, which had no `ExtendJon` group.
+const PRE_STRIVE_BOX_TYPES: [BoxType; 17] = [
+    BoxType::Hurtbox,
+    BoxType::Hitbox,
+    BoxType::ExPoint,
+    BoxType::ExRect,
+    BoxType::ExVector,
+    BoxType::Push,
+    BoxType::TempCenter,
+    BoxType::Neck,
+    BoxType::Abdominal,
+    BoxType::AttackVsPush,
+    BoxType::SpGuard,
+    BoxType::RLeg,
+    BoxType::LLeg,
+    BoxType::Private0,
+    BoxType::Private1,
+    BoxType::Private2,
+    BoxType::Private3,
+];
+
+/// The games whose collision files the editor can open: the Unreal-era Arc System Works titles,
+/// whose FPAC archives and jonbins arcsys reads. Everything that differs between titles
+/// (how their files are parsed, roster, asset layout, box kinds and their colors) is looked up here.
+#[derive(Copy, Clone, PartialEq, Eq, PartialOrd, Ord, Debug, Serialize, Deserialize)]
+pub enum Game {
+    Strive,
+    Gbvs,
+}
+
+impl Default for Game {
+    fn default() -> Self {
+        Game::Strive
+    }
+}
+
+impl Game {
+    pub const ALL: [Game; 2] = [Game::Strive, Game::Gbvs];
+
+    pub fn name(self) -> &'static str {
+        match self {
+            Game::Strive => "Guilty Gear -Strive-",
+            Game::Gbvs => "Granblue Fantasy Versus",
+        }
+    }
+
+    /// Both games share Strive's FPAC layout.
+    pub fn parse_pac(self, bytes: &[u8]) -> Result<GGSTPac, String> {
+        GGSTPac::parse(bytes).map_err(|e| e.to_string())
+    }
+
+    /// The jonbins of the games before Strive have a shorter header, which arcsys needs to be
+    /// told about.
+    pub fn parse_jonbin(self, bytes: &[u8]) -> Result<GGSTJonBin, String> {
+        match self {
+            Game::Strive => GGSTJonBin::parse(bytes, false),
+            Game::Gbvs => GGSTJonBin::parse(bytes, true),
+        }
+        .map_err(|e| e.to_string())
+    }
+
+    /// Path of an asset on the asset source. Strive files live at the root so that existing
+    /// mirrors keep working, while every other game gets its own directory.
+    pub fn asset_path(self, path: &str) -> String {
+        match self {
+            Game::Strive => path.to_string(),
+            Game::Gbvs => format!("gbvs/{}", path),
+        }
+    }
+
+    pub fn command_db(self) -> &'static str {
+        match self {
+            Game::Strive => "rons/ggst.ron",
+            Game::Gbvs => "rons/gbvs.ron",
+        }
+    }
+
+    /// The built-in roster as (code, full name) pairs. Codes are the ones used in the
+    /// collision and script file names.
+    pub fn roster(self) -> &'static [(&'static str, &'static str)] {
+        match self {
+            Game::Strive => &[
+                ("SOL", "Sol Badguy"),
+                ("KYK", "Ky Kiske"),
+                ("MAY", "May"),
+                ("AXL", "Axl Low"),
+                ("CHP", "Chipp Zanuff"),
+                ("POT", "Potemkin"),
+                ("FAU", "Faust"),
+                ("MLL", "Millia Rage"),
+                ("ZAT", "Zato-1"),
+                ("RAM", "Ramlethal Valentine"),
+                ("LEO", "Leo Whitefang"),
+                ("NAG", "Nagoriyuki"),
+                ("GIO", "Giovanna"),
+                ("ANJ", "Anji Mito"),
+                ("INO", "I-No"),
+                ("GLD", "Goldlewis Dickinson"),
+                ("JKO", "Jack-O'"),
+                ("COS", "Happy Chaos"),
+                ("BKN", "Baiken"),
+                ("TST", "Testament"),
+            ],
+            Game::Gbvs => &[
+                ("GRN", "Gran"),
+                ("KAT", "Katalina"),
+                ("CHA", "Charlotta"),
+                ("LAN", "Lancelot"),
+                ("FER", "Ferry"),
+                ("LOW", "Lowain"),
+                ("LAD", "Ladiva"),
+                ("PER", "Percival"),
+                ("MET", "Metera"),
+                ("ZET", "Zeta"),
+                ("VAS", "Vaseraga"),
+                ("NAR", "Narmaya"),
+                ("SOR", "Soriz"),
+                ("DJE", "Djeeta"),
+                ("BEL", "Beelzebub"),
+                ("ZOO", "Zooey"),
+                ("BLL", "Belial"),
+                ("CAG", "Cagliostro"),
+                ("YUE", "Yuel"),
+                ("ANR", "Anre"),
+                ("EUS", "Eustace"),
+                ("SEO", "Seox"),
+                ("VIR", "Vira"),
+                ("ANI", "Anila"),
+                ("SIE", "Siegfried"),
+                ("GRI", "Grimnir"),
+                ("NIE", "Nier"),
+                ("LUC", "Lucilius"),
+            ],
+        }
+    }
+
+    /// The box kinds this game's jonbins use, in the order their groups are stored.
+    pub fn box_types(self) -> &'static [BoxType] {
+        match self {
+            Game::Strive => &BoxType::ALL,
+            Game::Gbvs => &PRE_STRIVE_BOX_TYPES,
+        }
+    }
+
+    pub fn box_color(self, kind: BoxType) -> Color32 {
+        match (self, kind) {
+            (Game::Strive, BoxType::Hurtbox) => Color32::GREEN,
+            (Game::Strive, BoxType::Hitbox) => Color32::RED,
+            (Game::Strive, BoxType::Push) => Color32::DARK_BLUE,
+            (Game::Gbvs, BoxType::Hurtbox) => Color32::from_rgb(0, 160, 255),
+            (Game::Gbvs, BoxType::Hitbox) => Color32::from_rgb(255, 64, 64),
+            (Game::Gbvs, BoxType::Push) => Color32::from_rgb(170, 90, 255),
+            (_, kind) => shared_box_color(kind),
+        }
+    }
+}
+
+/// Colors of the kinds that look the same in every game.
+fn shared_box_color(kind: BoxType) -> Color32 {
+    match kind {
+        BoxType::Hurtbox => Color32::GREEN,
+        BoxType::Hitbox => Color32::RED,
+        BoxType::ExPoint => Color32::BLUE,
+        BoxType::ExRect => Color32::GOLD,
+        BoxType::ExVector => Color32::YELLOW,
+        BoxType::Push => Color32::DARK_BLUE,
+        BoxType::TempCenter => Color32::LIGHT_GREEN,
+        BoxType::Neck => Color32::LIGHT_RED,
+        BoxType::Abdominal => Color32::LIGHT_BLUE,
+        BoxType::AttackVsPush => Color32::LIGHT_YELLOW,
+        BoxType::SpGuard => Color32::DEBUG_COLOR,
+        BoxType::RLeg => Color32::KHAKI,
+        BoxType::LLeg => Color32::BROWN,
+        BoxType::Private0 => Color32::GRAY,
+        BoxType::Private1 => Color32::BLACK,
+        BoxType::Private2 => Color32::LIGHT_GRAY,
+        BoxType::Private3 => Color32::DARK_GRAY,
+        BoxType::ExtendJon => Color32::DARK_RED,
+    }
+}
@@ -0,0 +1,150 @@
+use arcsys::ggst::jonbin::HitBox;
+
+/// A reversible change to the boxes of one sprite, stored as the sprite's box groups
+/// before and after the change.
+pub struct Edit {
+    pub sprite: String,
+    pub description: String,
+    pub before: Vec<Vec<HitBox>>,
+    pub after: Vec<Vec<HitBox>>,
+    pub selected_before: Option<(usize, usize)>,
+    pub selected_after: Option<(usize, usize)>,
+    /// Consecutive mergeable edits of the same box collapse into a single history entry,
+    /// so typing a value in the inspector doesn't leave one entry per keystroke.
+    pub mergeable: bool,
+}
+
+#[derive(Default)]
+pub struct History {
+    undo: Vec<Edit>,
+    redo: Vec<Edit>,
+}
+
+impl History {
+    pub fn push(&mut self, edit: Edit) {
+        self.redo.clear();
+        if let Some(last) = self.undo.last_mut() {
+            if edit.mergeable
+                && last.mergeable
+                && last.sprite == edit.sprite
+                && last.description == edit.description
+                && last.selected_after == edit.selected_before
+            {
+                last.after = edit.after;
+                last.selected_after = edit.selected_after;
+                return;
+            }
+        }
+        self.undo.push(edit);
+    }
+
+    /// Moves the latest edit onto the redo stack and returns it so its `before` state can be restored.
+    pub fn undo(&mut self) -> Option<&Edit> {
+        let edit = self.undo.pop()?;
+        self.redo.push(edit);
+        self.redo.last()
+    }
+
+    /// Moves the latest undone edit back onto the undo stack and returns it so its `after` state can be restored.
+    pub fn redo(&mut self) -> Option<&Edit> {
+        let edit = self.redo.pop()?;
+        self.undo.push(edit);
+        self.undo.last()
+    }
+
+    pub fn can_undo(&self) -> bool {
+        !self.undo.is_empty()
+    }
+
+    pub fn can_redo(&self) -> bool {
+        !self.redo.is_empty()
+    }
+
+    /// Edits that can be undone, oldest first.
+    pub fn undo_list(&self) -> &[Edit] {
+        &self.undo
+    }
+
+    /// Edits that can be redone, the next one to redo last.
+    pub fn redo_list(&self) -> &[Edit] {
+        &self.redo
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use arcsys::ggst::jonbin::Rect;
+
+    fn boxes(x_offset: f32) -> Vec<Vec<HitBox>> {
+        vec![vec![HitBox { kind: 0, rect: Rect { x_offset, y_offset: 0.0, width: 10.0, height: 10.0 } }]]
+    }
+
+    fn edit(sprite: &str, description: &str, before: f32, after: f32, mergeable: bool) -> Edit {
+        Edit {
+            sprite: sprite.to_string(),
+            description: description.to_string(),
+            before: boxes(before),
+            after: boxes(after),
+            selected_before: Some((0, 0)),
+            selected_after: Some((0, 0)),
+            mergeable,
+        }
+    }
+
+    fn x_offsets(edits: &[Edit]) -> Vec<(f32, f32)> {
+        edits.iter().map(|edit| (edit.before[0][0].rect.x_offset, edit.after[0][0].rect.x_offset)).collect()
+    }
+
+    #[test]
+    fn consecutive_mergeable_edits_of_the_same_box_collapse() {
+        let mut history = History::default();
+        history.push(edit("sol000_00", "Edit X", 0.0, 1.0, true));
+        history.push(edit("sol000_00", "Edit X", 1.0, 2.0, true));
+        history.push(edit("sol000_00", "Edit X", 2.0, 3.0, true));
+        assert_eq!(x_offsets(history.undo_list()), vec![(0.0, 3.0)]);
+    }
+
+    #[test]
+    fn edits_only_merge_when_everything_matches() {
+        let cases = [
+            ("not mergeable", edit("sol000_00", "Edit X", 1.0, 2.0, false)),
+            ("other sprite", edit("sol000_01", "Edit X", 1.0, 2.0, true)),
+            ("other description", edit("sol000_00", "Edit Y", 1.0, 2.0, true)),
+            ("other box", Edit { selected_before: Some((0, 1)), ..edit("sol000_00", "Edit X", 1.0, 2.0, true) }),
+        ];
+        for (name, second) in cases {
+            let mut history = History::default();
+            history.push(edit("sol000_00", "Edit X", 0.0, 1.0, true));
+            history.push(second);
+            assert_eq!(x_offsets(history.undo_list()), vec![(0.0, 1.0), (1.0, 2.0)], "{}", name);
+        }
+        // A mergeable edit doesn't merge into one that wasn't.
+        let mut history = History::default();
+        history.push(edit("sol000_00", "Edit X", 0.0, 1.0, false));
+        history.push(edit("sol000_00", "Edit X", 1.0, 2.0, true));
+        assert_eq!(history.undo_list().len(), 2);
+    }
+
+    #[test]
+    fn undo_and_redo_walk_the_stacks_in_order() {
+        let mut history = History::default();
+        assert!(history.undo().is_none());
+        for (before, after) in [(0.0, 1.0), (1.0, 2.0), (2.0, 3.0)] {
+            history.push(edit("sol000_00", "Move", before, after, false));
+        }
+        assert_eq!(history.undo().map(|edit| edit.after[0][0].rect.x_offset), Some(3.0));
+        assert_eq!(history.undo().map(|edit| edit.after[0][0].rect.x_offset), Some(2.0));
+        assert_eq!(x_offsets(history.undo_list()), vec![(0.0, 1.0)]);
+        // The next edit to redo comes last.
+        assert_eq!(x_offsets(history.redo_list()), vec![(2.0, 3.0), (1.0, 2.0)]);
+        assert_eq!(history.redo().map(|edit| edit.after[0][0].rect.x_offset), Some(2.0));
+        assert!(history.can_redo());
+
+        // A new edit throws away what could still be redone.
+        history.push(edit("sol000_00", "Move", 2.0, 5.0, false));
+        assert!(!history.can_redo());
+        assert!(history.redo().is_none());
+        assert_eq!(x_offsets(history.undo_list()), vec![(0.0, 1.0), (1.0, 2.0), (2.0, 5.0)]);
+    }
+}
@@ -0,0 +1,275 @@
+use std::collections::BTreeMap;
+use arcsys::ggst::jonbin::{GGSTJonBin, HitBox, Rect};
+use serde::{Deserialize, Serialize};
+use super::game::{BoxType, Game};
+
+/// Version of the layout below. Bumped whenever a field changes meaning, so that older
+/// exports can be told apart.
+pub const SCHEMA_VERSION: u32 = 1;
+
+/// Collision data as exported to JSON. A whole PAC looks like
+///
+/// ```json
+/// {
+///   "version": 1,
+///   "kind": "pac",
+///   "jonbins": [
+///     {
+///       "name": "sol000_00",
+///       "groups": [
+///         { "kind": "Hurtbox", "boxes": [{ "x": -50.0, "y": -150.0, "width": 100.0, "height": 150.0 }] }
+///       ]
+///     }
+///   ]
+/// }
+/// ```
+///
+/// while a single sprite has `"kind": "jonbin"` and the `name` and `groups` of one jonbin in
+/// place of `jonbins`. A group's `kind` is a `BoxType` name, or the raw number for kinds the
+/// editor doesn't know. Empty groups are left out, and jonbins and groups are sorted so that
+/// unchanged data exports identically. On import a group's kind has to be one of the game's box
+/// kinds or a group the jonbin already has, so the jonbin keeps the layout the game expects.
+#[derive(Serialize, Deserialize, Clone, PartialEq, Debug)]
+pub struct Document {
+    pub version: u32,
+    #[serde(flatten)]
+    pub content: MetaKind,
+}
+
+#[derive(Serialize, Deserialize, Clone, PartialEq, Debug)]
+#[serde(tag = "kind", rename_all = "lowercase")]
+pub enum MetaKind {
+    Pac { jonbins: Vec<JonBinDoc> },
+    JonBin(JonBinDoc),
+}
+
+#[derive(Serialize, Deserialize, Clone, PartialEq, Debug)]
+pub struct JonBinDoc {
+    pub name: String,
+    pub groups: Vec<GroupDoc>,
+}
+
+#[derive(Serialize, Deserialize, Clone, PartialEq, Debug)]
+pub struct GroupDoc {
+    pub kind: KindDoc,
+    pub boxes: Vec<RectDoc>,
+}
+
+#[derive(Serialize, Deserialize, Copy, Clone, PartialEq, Debug)]
+#[serde(untagged)]
+pub enum KindDoc {
+    Known(BoxType),
+    Unknown(u32),
+}
+
+#[derive(Serialize, Deserialize, Copy, Clone, PartialEq, Debug)]
+pub struct RectDoc {
+    pub x: f32,
+    pub y: f32,
+    pub width: f32,
+    pub height: f32,
+}
+
+impl From<u32> for KindDoc {
+    fn from(kind: u32) -> Self {
+        match BoxType::try_from(kind) {
+            Ok(kind) => KindDoc::Known(kind),
+            Err(_) => KindDoc::Unknown(kind),
+        }
+    }
+}
+
+impl KindDoc {
+    pub fn value(self) -> u32 {
+        match self {
+            KindDoc::Known(kind) => kind as u32,
+            KindDoc::Unknown(kind) => kind,
+        }
+    }
+}
+
+impl JonBinDoc {
+    pub fn new(name: &str, jonb: &GGSTJonBin) -> JonBinDoc {
+        JonBinDoc::from_boxes(name, &jonb.boxes)
+    }
+
+    fn from_boxes(name: &str, boxes: &[Vec<HitBox>]) -> JonBinDoc {
+        let groups = boxes
+            .iter()
+            .enumerate()
+            .filter(|(_, group)| !group.is_empty())
+            .map(|(kind, group)| GroupDoc {
+                kind: KindDoc::from(kind as u32),
+                boxes: group
+                    .iter()
+                    .map(|hitbox| RectDoc {
+                        x: hitbox.rect.x_offset,
+                        y: hitbox.rect.y_offset,
+                        width: hitbox.rect.width,
+                        height: hitbox.rect.height,
+                    })
+                    .collect(),
+            })
+            .collect();
+        JonBinDoc { name: name.to_string(), groups }
+    }
+
+    /// The box groups of `current` with every box replaced by the ones in this document. Groups
+    /// stay indexed by kind, and `current` keeps at least as many groups as it had so the jonbin
+    /// header describes the same layout. Fails on kinds that are neither one of `game`'s box
+    /// kinds nor a group `current` already has.
+    pub fn apply(&self, current: &[Vec<HitBox>], game: Game) -> Result<Vec<Vec<HitBox>>, String> {
+        let mut boxes: Vec<Vec<HitBox>> = current.iter().map(|_| Vec::new()).collect();
+        for group in &self.groups {
+            let kind = group.kind.value();
+            let index = kind as usize;
+            if index >= current.len() && !game.box_types().iter().any(|known| *known as u32 == kind) {
+                return Err(format!("{}: {} has no box kind {}", self.name, game.name(), kind));
+            }
+            if boxes.len() <= index {
+                boxes.resize_with(index + 1, Vec::new);
+            }
+            boxes[index].extend(group.boxes.iter().map(|rect| HitBox {
+                kind,
+                rect: Rect {
+                    x_offset: rect.x,
+                    y_offset: rect.y,
+                    width: rect.width,
+                    height: rect.height,
+                },
+            }));
+        }
+        Ok(boxes)
+    }
+}
+
+impl Document {
+    pub fn pac(jonbins: &BTreeMap<String, GGSTJonBin>) -> Document {
+        Document {
+            version: SCHEMA_VERSION,
+            content: MetaKind::Pac {
+                jonbins: jonbins.iter().map(|(name, jonb)| JonBinDoc::new(name, jonb)).collect(),
+            },
+        }
+    }
+
+    pub fn jonbin(name: &str, jonb: &GGSTJonBin) -> Document {
+        Document {
+            version: SCHEMA_VERSION,
+            content: MetaKind::JonBin(JonBinDoc::new(name, jonb)),
+        }
+    }
+
+    pub fn parse(bytes: &[u8]) -> Result<Document, String> {
+        let document: Document = serde_json::from_slice(bytes).map_err(|e| e.to_string())?;
+        if document.version > SCHEMA_VERSION {
+            return Err(format!(
+                "The file uses version {} of the schema, but this editor only knows up to version {}",
+                document.version, SCHEMA_VERSION
+            ));
+        }
+        Ok(document)
+    }
+
+    pub fn to_bytes(&self) -> Result<Vec<u8>, String> {
+        serde_json::to_vec_pretty(self).map_err(|e| e.to_string())
+    }
+
+    pub fn jonbins(&self) -> &[JonBinDoc] {
+        match &self.content {
+            MetaKind::Pac { jonbins } => jonbins,
+            MetaKind::JonBin(jonbin) => std::slice::from_ref(jonbin),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn hitbox(kind: BoxType, x_offset: f32, y_offset: f32, width: f32, height: f32) -> HitBox {
+        HitBox { kind: kind as u32, rect: Rect { x_offset, y_offset, width, height } }
+    }
+
+    fn flatten(boxes: &[Vec<HitBox>]) -> Vec<(usize, u32, [f32; 4])> {
+        boxes
+            .iter()
+            .enumerate()
+            .flat_map(|(group, hitboxes)| {
+                hitboxes.iter().map(move |hitbox| {
+                    (group, hitbox.kind, [hitbox.rect.x_offset, hitbox.rect.y_offset, hitbox.rect.width, hitbox.rect.height])
+                })
+            })
+            .collect()
+    }
+
+    fn sample() -> Vec<Vec<HitBox>> {
+        vec![
+            vec![hitbox(BoxType::Hurtbox, -50.0, -150.0, 100.0, 150.0)],
+            vec![hitbox(BoxType::Hitbox, 20.0, -120.0, -80.5, 40.0), hitbox(BoxType::Hitbox, 0.0, 0.0, 10.0, 10.0)],
+            vec![],
+            vec![],
+            vec![hitbox(BoxType::ExVector, 0.0, -100.0, 30.0, -15.0)],
+        ]
+    }
+
+    #[test]
+    fn export_then_import_gives_the_same_boxes() {
+        let boxes = sample();
+        let document = Document {
+            version: SCHEMA_VERSION,
+            content: MetaKind::JonBin(JonBinDoc::from_boxes("sol000_00", &boxes)),
+        };
+        let parsed = Document::parse(&document.to_bytes().unwrap()).unwrap();
+        assert_eq!(parsed, document);
+        let empty: Vec<Vec<HitBox>> = boxes.iter().map(|_| vec![]).collect();
+        let applied = parsed.jonbins()[0].apply(&empty, Game::Strive).unwrap();
+        assert_eq!(flatten(&applied), flatten(&boxes));
+        assert_eq!(applied.len(), boxes.len());
+    }
+
+    #[test]
+    fn schema_is_stable() {
+        let document = Document {
+            version: SCHEMA_VERSION,
+            content: MetaKind::JonBin(JonBinDoc::from_boxes("sol000_00", &sample())),
+        };
+        let expected = serde_json::json!({
+            "version": 1,
+            "kind": "jonbin",
+            "name": "sol000_00",
+            "groups": [
+                { "kind": "Hurtbox", "boxes": [{ "x": -50.0, "y": -150.0, "width": 100.0, "height": 150.0 }] },
+                {
+                    "kind": "Hitbox",
+                    "boxes": [
+                        { "x": 20.0, "y": -120.0, "width": -80.5, "height": 40.0 },
+                        { "x": 0.0, "y": 0.0, "width": 10.0, "height": 10.0 }
+                    ]
+                },
+                { "kind": "ExVector", "boxes": [{ "x": 0.0, "y": -100.0, "width": 30.0, "height": -15.0 }] }
+            ]
+        });
+        assert_eq!(serde_json::to_value(&document).unwrap(), expected);
+    }
+
+    #[test]
+    fn unknown_kinds_are_rejected() {
+        let current = sample();
+        let import = |kind: serde_json::Value| {
+            let document = serde_json::json!({
+                "version": 1,
+                "kind": "jonbin",
+                "name": "sol000_00",
+                "groups": [{ "kind": kind, "boxes": [{ "x": 0.0, "y": 0.0, "width": 1.0, "height": 1.0 }] }]
+            });
+            Document::parse(document.to_string().as_bytes()).unwrap().jonbins()[0].apply(&current, Game::Gbvs)
+        };
+        assert!(import(serde_json::json!(4294967295u32)).is_err());
+        assert!(import(serde_json::json!(17)).is_err(), "ExtendJon isn't a GBVS box kind");
+        assert!(import(serde_json::json!("ExtendJon")).is_err());
+        // Kinds the game knows, or that the jonbin already has a group for, are fine.
+        assert_eq!(import(serde_json::json!("LLeg")).unwrap().len(), BoxType::LLeg as usize + 1);
+        assert_eq!(import(serde_json::json!(3)).unwrap().len(), current.len());
+    }
+}
@@ -0,0 +1,112 @@
+use std::collections::BTreeMap;
+use eframe::{egui, epaint::Color32};
+use serde::{Deserialize, Serialize};
+use super::game::{BoxType, Game};
+
+/// How boxes of one kind are drawn on the canvas.
+#[derive(Clone, Copy, PartialEq, Debug, Serialize, Deserialize)]
+pub struct BoxStyle {
+    pub visible: bool,
+    /// sRGB, without alpha; `opacity` takes care of that.
+    pub color: [u8; 3],
+    pub fill: bool,
+    /// Opacity of the outline. The fill, if any, is drawn at a quarter of it so overlapping
+    /// boxes stay readable.
+    pub opacity: f32,
+}
+
+impl BoxStyle {
+    fn default_for(game: Game, kind: BoxType) -> BoxStyle {
+        let color = game.box_color(kind);
+        BoxStyle {
+            visible: true,
+            color: [color.r(), color.g(), color.b()],
+            fill: false,
+            opacity: 1.0,
+        }
+    }
+
+    pub fn stroke_color(&self) -> Color32 {
+        self.with_alpha(self.opacity)
+    }
+
+    pub fn fill_color(&self) -> Option<Color32> {
+        self.fill.then(|| self.with_alpha(self.opacity * 0.25))
+    }
+
+    fn with_alpha(&self, alpha: f32) -> Color32 {
+        let [r, g, b] = self.color;
+        Color32::from_rgba_unmultiplied(r, g, b, (alpha.clamp(0.0, 1.0) * 255.0).round() as u8)
+    }
+}
+
+/// The user's changes to how box kinds are drawn, on top of each game's defaults. Kept per game,
+/// since the defaults differ between games, and by `BoxType` name so that saved settings survive
+/// the enum being reordered.
+#[derive(Clone, Default, Debug, Serialize, Deserialize)]
+pub struct Legend {
+    overrides: BTreeMap<Game, BTreeMap<String, BoxStyle>>,
+}
+
+impl Legend {
+    pub fn style(&self, game: Game, kind: BoxType) -> BoxStyle {
+        self.overrides
+            .get(&game)
+            .and_then(|overrides| overrides.get(kind.name()))
+            .copied()
+            .unwrap_or_else(|| BoxStyle::default_for(game, kind))
+    }
+
+    /// Whether boxes of the raw `kind` are drawn. Kinds this editor doesn't know are always shown.
+    pub fn is_visible(&self, game: Game, kind: u32) -> bool {
+        BoxType::try_from(kind).map_or(true, |kind| self.style(game, kind).visible)
+    }
+
+    fn set_style(&mut self, game: Game, kind: BoxType, style: BoxStyle) {
+        let overrides = self.overrides.entry(game).or_default();
+        if style == BoxStyle::default_for(game, kind) {
+            overrides.remove(kind.name());
+        }
+        else {
+            overrides.insert(kind.name().to_string(), style);
+        }
+    }
+
+    fn is_overridden(&self, game: Game, kind: BoxType) -> bool {
+        self.overrides.get(&game).map_or(false, |overrides| overrides.contains_key(kind.name()))
+    }
+
+    /// Lists every box kind of `game` with how many boxes of it the current sprite has, and
+    /// controls for how it is drawn. `counts` is keyed by raw kind so unknown kinds show up too.
+    pub fn ui(&mut self, ui: &mut egui::Ui, game: Game, counts: &BTreeMap<u32, usize>) {
+        egui::Grid::new("legend").striped(true).show(ui, |ui| {
+            ui.label("Show");
+            ui.label("Kind");
+            ui.label("Boxes");
+            ui.label("Color");
+            ui.label("Fill");
+            ui.label("Opacity");
+            ui.end_row();
+            for &kind in game.box_types() {
+                let mut style = self.style(game, kind);
+                ui.checkbox(&mut style.visible, "");
+                ui.label(kind.name());
+                ui.label(counts.get(&(kind as u32)).copied().unwrap_or(0).to_string());
+                ui.color_edit_button_srgb(&mut style.color);
+                ui.checkbox(&mut style.fill, "");
+                ui.add(egui::Slider::new(&mut style.opacity, 0.0..=1.0).show_value(false));
+                if ui.add_enabled(self.is_overridden(game, kind), egui::Button::new("Reset")).clicked() {
+                    style = BoxStyle::default_for(game, kind);
+                }
+                ui.end_row();
+                self.set_style(game, kind, style);
+            }
+            for (&kind, count) in counts.iter().filter(|(&kind, _)| BoxType::try_from(kind).is_err()) {
+                ui.label("");
+                ui.label(format!("Unknown ({})", kind));
+                ui.label(count.to_string());
+                ui.end_row();
+            }
+        });
+    }
+}
@@ -1,9 +1,251 @@
+use std::io::{Cursor, Read};
 use arcsys::{ggst::pac::{GGSTPac}};
+use super::game::Game;
+use byteorder::{LittleEndian, ReadBytesExt};
+use eframe::egui;
+use poll_promise::Promise;
 
-pub fn open_file(file_buf: Vec<u8>) -> Result<GGSTPac, String> {
-    match GGSTPac::parse(&file_buf)
+/// A parsed collision PAC, along with where each of its files starts in the archive.
+pub struct ColFile {
+    pub pac: GGSTPac,
+    /// Byte offsets of `pac.files`, in the same order. Empty if the file table couldn't be read.
+    pub offsets: Vec<u64>,
+    /// The file as it was read, for checking what writing it back changes.
+    pub bytes: Vec<u8>,
+}
+
+pub fn open_file(file_buf: Vec<u8>, game: Game) -> Result<ColFile, String> {
+    let offsets = entry_offsets(&file_buf);
+    game.parse_pac(&file_buf).map(|pac| ColFile { pac, offsets, bytes: file_buf })
+}
+
+/// Reads the FPAC file table to find where each file's data starts. The 0x20 byte header holds
+/// the magic, data start, total size, file count, flags and name length; each table entry is the
+/// name followed by the file's index, offset from the data start and size, padded to 16 bytes.
+fn entry_offsets(file_buf: &[u8]) -> Vec<u64> {
+    fn read_table(file_buf: &[u8]) -> std::io::Result<Vec<u64>> {
+        let mut cursor = Cursor::new(file_buf);
+        let mut magic = [0; 4];
+        cursor.read_exact(&mut magic)?;
+        if &magic != b"FPAC" {
+            return Ok(vec![]);
+        }
+        let data_start = cursor.read_u32::<LittleEndian>()? as u64;
+        let _total_size = cursor.read_u32::<LittleEndian>()?;
+        let file_count = cursor.read_u32::<LittleEndian>()? as u64;
+        let _flags = cursor.read_u32::<LittleEndian>()?;
+        let name_length = cursor.read_u32::<LittleEndian>()? as u64;
+        let entry_size = (name_length + 12 + 15) / 16 * 16;
+
+        let mut offsets = vec![];
+        for index in 0..file_count {
+            cursor.set_position(0x20 + index * entry_size + name_length + 4);
+            let offset = data_start + cursor.read_u32::<LittleEndian>()? as u64;
+            if offset >= file_buf.len() as u64 {
+                return Ok(vec![]);
+            }
+            offsets.push(offset);
+        }
+        Ok(offsets)
+    }
+    read_table(file_buf).unwrap_or_default()
+}
+
+/// The inputs of the editor that can be replaced with a file from disk.
+#[derive(Copy, Clone, PartialEq, Debug)]
+pub enum Slot {
+    Collision,
+    CharScript,
+    EffectScript,
+    GameDb,
+    Image,
+    /// Collision data exported as JSON, imported into the loaded PAC.
+    Json,
+}
+
+impl Slot {
+    pub fn description(self) -> &'static str {
+        match self {
+            Slot::Collision => "Collision PAC",
+            Slot::CharScript => "Character script",
+            Slot::EffectScript => "Effect script",
+            Slot::GameDb => "Command DB",
+            Slot::Image => "Sprite image",
+            Slot::Json => "Collision JSON",
+        }
+    }
+
+    pub fn extensions(self) -> &'static [&'static str] {
+        match self {
+            Slot::Collision => &["pac"],
+            Slot::CharScript | Slot::EffectScript => &["bbscript"],
+            Slot::GameDb => &["ron"],
+            Slot::Image => &["png"],
+            Slot::Json => &["json"],
+        }
+    }
+
+    /// Works out which slot a file belongs in from its contents where they are recognizable,
+    /// and from its name otherwise. Effect scripts are told apart by the `EF` suffix the game uses.
+    pub fn classify(name: &str, bytes: &[u8]) -> Option<Slot> {
+        if bytes.starts_with(b"FPAC") {
+            return Some(Slot::Collision);
+        }
+        if bytes.starts_with(b"\x89PNG") {
+            return Some(Slot::Image);
+        }
+        let name = name.to_lowercase();
+        let (stem, extension) = name.rsplit_once('.')?;
+        match extension {
+            "pac" => Some(Slot::Collision),
+            "bbscript" if stem.ends_with("ef") => Some(Slot::EffectScript),
+            "bbscript" => Some(Slot::CharScript),
+            "ron" => Some(Slot::GameDb),
+            "png" => Some(Slot::Image),
+            "json" => Some(Slot::Json),
+            _ => None,
+        }
+    }
+}
+
+pub struct LocalFile {
+    pub name: String,
+    pub bytes: Vec<u8>,
+}
+
+/// Gets the contents of a file dropped onto the window. The web backend hands over the bytes
+/// directly, while native only gives a path to read.
+pub fn read_dropped(file: &egui::DroppedFile) -> Result<LocalFile, String> {
+    let name = match &file.path {
+        Some(path) if file.name.is_empty() => path.file_name().map(|name| name.to_string_lossy().into_owned()).unwrap_or_default(),
+        _ => file.name.clone(),
+    };
+    if let Some(bytes) = &file.bytes {
+        return Ok(LocalFile { name, bytes: bytes.to_vec() });
+    }
+    match &file.path {
+        Some(path) => match std::fs::read(path) {
+            Ok(bytes) => Ok(LocalFile { name, bytes }),
+            Err(e) => Err(format!("Could not read {}: {}", path.display(), e)),
+        },
+        None => Err(format!("No data was dropped for {}", name)),
+    }
+}
+
+/// Result of asking the user for a file: `None` if they cancelled.
+pub type PickedFile = Option<Result<LocalFile, String>>;
+
+/// Shows a native open dialog for `slot` and reads the chosen file.
+#[cfg(not(target_arch = "wasm32"))]
+pub fn pick_file(slot: Slot, _ctx: &egui::Context) -> Promise<PickedFile> {
+    let picked = rfd::FileDialog::new()
+        .add_filter(slot.description(), slot.extensions())
+        .pick_file()
+        .map(|path| {
+            let name = path.file_name().map(|name| name.to_string_lossy().into_owned()).unwrap_or_default();
+            match std::fs::read(&path) {
+                Ok(bytes) => Ok(LocalFile { name, bytes }),
+                Err(e) => Err(format!("Could not read {}: {}", path.display(), e)),
+            }
+        });
+    Promise::from_ready(picked)
+}
+
+/// Opens the browser's file picker through a temporary `<input type=file>` and reads the
+/// chosen file with a `FileReader`. If the user cancels, the promise is never resolved.
+#[cfg(target_arch = "wasm32")]
+pub fn pick_file(slot: Slot, ctx: &egui::Context) -> Promise<PickedFile> {
+    use std::{cell::RefCell, rc::Rc};
+    use wasm_bindgen::{closure::Closure, JsCast};
+
+    let (sender, promise) = Promise::new();
+    // Shared so that whichever callback finishes first (or fails) can resolve the promise.
+    let sender = Rc::new(RefCell::new(Some(sender)));
+    let send = {
+        let ctx = ctx.clone();
+        move |sender: &Rc<RefCell<Option<poll_promise::Sender<PickedFile>>>>, picked: PickedFile| {
+            if let Some(sender) = sender.borrow_mut().take() {
+                sender.send(picked);
+                ctx.request_repaint();
+            }
+        }
+    };
+
+    let input: web_sys::HtmlInputElement = match web_sys::window()
+        .and_then(|window| window.document())
+        .and_then(|document| document.create_element("input").ok())
+        .and_then(|element| element.dyn_into().ok())
     {
-        Ok(file) => return Ok(file),
-        Err(e) => return Err(format!("{}", e)),
+        Some(input) => input,
+        None => {
+            send(&sender, Some(Err("Could not create a file input".to_string())));
+            return promise;
+        }
     };
-}
\ No newline at end of file
+    input.set_type("file");
+    let accept: Vec<String> = slot.extensions().iter().map(|extension| format!(".{}", extension)).collect();
+    input.set_accept(&accept.join(","));
+
+    let onchange = {
+        let input = input.clone();
+        Closure::once(move || {
+            let file = match input.files().and_then(|files| files.get(0)) {
+                Some(file) => file,
+                None => {
+                    send(&sender, None);
+                    return;
+                }
+            };
+            let reader = match web_sys::FileReader::new() {
+                Ok(reader) => reader,
+                Err(e) => {
+                    send(&sender, Some(Err(format!("{:?}", e))));
+                    return;
+                }
+            };
+            let name = file.name();
+            let onload = {
+                let reader = reader.clone();
+                let sender = sender.clone();
+                let send = send.clone();
+                Closure::once(move || {
+                    let picked = match reader.result() {
+                        Ok(buffer) => Ok(LocalFile { name, bytes: js_sys::Uint8Array::new(&buffer).to_vec() }),
+                        Err(e) => Err(format!("{:?}", e)),
+                    };
+                    send(&sender, Some(picked));
+                })
+            };
+            reader.set_onload(Some(onload.as_ref().unchecked_ref()));
+            onload.forget();
+            if let Err(e) = reader.read_as_array_buffer(&file) {
+                send(&sender, Some(Err(format!("{:?}", e))));
+            }
+        })
+    };
+    input.set_onchange(Some(onchange.as_ref().unchecked_ref()));
+    onchange.forget();
+    input.click();
+    promise
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const FIXTURE: &[u8] = include_bytes!("../../tests/fixtures/strive/COL_fixture.pac");
+
+    #[test]
+    fn entry_offsets_reads_the_file_table() {
+        // One 48-byte entry after the 0x20 byte header, so the only file starts at 0x50.
+        assert_eq!(entry_offsets(FIXTURE), vec![0x50]);
+    }
+
+    #[test]
+    fn entry_offsets_gives_up_on_anything_else() {
+        assert_eq!(entry_offsets(b"not a pac"), Vec::<u64>::new());
+        assert_eq!(entry_offsets(&FIXTURE[..0x30]), Vec::<u64>::new());
+        // An offset past the end of the file means the table can't be trusted.
+        assert_eq!(entry_offsets(&FIXTURE[..0x50]), Vec::<u64>::new());
+    }
+}
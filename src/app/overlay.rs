@@ -0,0 +1,134 @@
+use eframe::{egui, emath::{Align2, Pos2, Rect, Vec2}, epaint::{Color32, FontId, Stroke}};
+use serde::{Deserialize, Serialize};
+use super::view::Transform;
+
+/// Grid lines closer together than this many screen pixels aren't drawn.
+const MIN_GRID_SPACING: f32 = 4.0;
+
+/// Guides drawn behind the boxes. Saved with the app's settings.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct Overlays {
+    /// X and Y axes through the character's origin.
+    pub axes: bool,
+    pub ground: bool,
+    /// Height of the ground in game units. The origin sits on the ground while standing.
+    pub ground_y: f32,
+    pub grid: bool,
+    /// Distance between grid lines in game units.
+    pub grid_size: f32,
+}
+
+impl Default for Overlays {
+    fn default() -> Self {
+        Self {
+            axes: true,
+            ground: false,
+            ground_y: 0.0,
+            grid: false,
+            grid_size: 100.0,
+        }
+    }
+}
+
+impl Overlays {
+    pub fn ui(&mut self, ui: &mut egui::Ui) {
+        ui.checkbox(&mut self.axes, "Origin axes");
+        ui.horizontal(|ui| {
+            ui.checkbox(&mut self.ground, "Ground line at");
+            ui.add(egui::DragValue::new(&mut self.ground_y));
+        });
+        ui.horizontal(|ui| {
+            ui.checkbox(&mut self.grid, "Grid every");
+            ui.add(egui::DragValue::new(&mut self.grid_size).clamp_range(1.0..=10000.0));
+            ui.label("units");
+        });
+    }
+
+    /// Draws the enabled guides over the part of the world shown in `canvas`.
+    pub fn paint(&self, painter: &egui::Painter, view: &Transform, canvas: Rect) {
+        if self.grid && self.grid_size * view.zoom >= MIN_GRID_SPACING {
+            let stroke = Stroke{width: 1.0, color: Color32::from_gray(128).linear_multiply(0.3)};
+            let min = view.to_world(canvas.min);
+            let max = view.to_world(canvas.max);
+            let mut x = (min.x / self.grid_size).floor() * self.grid_size;
+            while x <= max.x {
+                let screen_x = view.to_screen(Pos2{x, y: 0.0}).x;
+                painter.line_segment([Pos2{x: screen_x, y: canvas.top()}, Pos2{x: screen_x, y: canvas.bottom()}], stroke);
+                x += self.grid_size;
+            }
+            let mut y = (min.y / self.grid_size).floor() * self.grid_size;
+            while y <= max.y {
+                let screen_y = view.to_screen(Pos2{x: 0.0, y}).y;
+                painter.line_segment([Pos2{x: canvas.left(), y: screen_y}, Pos2{x: canvas.right(), y: screen_y}], stroke);
+                y += self.grid_size;
+            }
+        }
+        if self.ground {
+            let screen_y = view.to_screen(Pos2{x: 0.0, y: self.ground_y}).y;
+            painter.line_segment(
+                [Pos2{x: canvas.left(), y: screen_y}, Pos2{x: canvas.right(), y: screen_y}],
+                Stroke{width: 2.0, color: Color32::BROWN},
+            );
+        }
+        if self.axes {
+            let origin = view.origin;
+            let stroke = Stroke{width: 1.0, color: Color32::WHITE.linear_multiply(0.6)};
+            painter.line_segment([Pos2{x: canvas.left(), y: origin.y}, Pos2{x: canvas.right(), y: origin.y}], stroke);
+            painter.line_segment([Pos2{x: origin.x, y: canvas.top()}, Pos2{x: origin.x, y: canvas.bottom()}], stroke);
+        }
+    }
+}
+
+/// Measures the distance between two clicked points in game units.
+#[derive(Default)]
+pub struct Ruler {
+    pub active: bool,
+    start: Option<Pos2>,
+    end: Option<Pos2>,
+}
+
+impl Ruler {
+    /// Sets the start of a new measurement, or its end if only the start is set. Points are
+    /// rounded to whole game units.
+    pub fn click(&mut self, world: Pos2) {
+        let point = world.round();
+        match (self.start, self.end) {
+            (Some(_), None) => self.end = Some(point),
+            _ => {
+                self.start = Some(point);
+                self.end = None;
+            }
+        }
+    }
+
+    pub fn clear(&mut self) {
+        self.start = None;
+        self.end = None;
+    }
+
+    /// Draws the measurement, following the pointer while only the start has been clicked.
+    pub fn paint(&self, painter: &egui::Painter, view: &Transform, pointer: Option<Pos2>) {
+        let start = match self.start {
+            Some(start) => start,
+            None => return,
+        };
+        let end = match self.end.or_else(|| pointer.map(|pointer| view.to_world(pointer).round())) {
+            Some(end) => end,
+            None => return,
+        };
+        let color = Color32::from_rgb(255, 0, 255);
+        let (start_screen, end_screen) = (view.to_screen(start), view.to_screen(end));
+        painter.line_segment([start_screen, end_screen], Stroke{width: 2.0, color});
+        for point in [start_screen, end_screen] {
+            painter.circle_filled(point, 3.0, color);
+        }
+        let delta = end - start;
+        painter.text(
+            end_screen + Vec2{x: 8.0, y: -8.0},
+            Align2::LEFT_BOTTOM,
+            format!("{:.0} units (dx {:.0}, dy {:.0})", delta.length(), delta.x, delta.y),
+            FontId::monospace(14.0),
+            color,
+        );
+    }
+}
@@ -0,0 +1,120 @@
+use std::collections::BTreeSet;
+use serde::{Deserialize, Serialize};
+use super::{error::LoadError, game::Game, source::AssetSource};
+
+/// File the roster is read from on a web source, relative to the game's asset directory.
+/// It holds a JSON list such as `[{"code": "SOL", "name": "Sol Badguy"}]`; `name` may be left out.
+pub const MANIFEST: &str = "roster.json";
+
+#[derive(Clone, PartialEq, Serialize, Deserialize)]
+pub struct Character {
+    pub code: String,
+    #[serde(default)]
+    pub name: String,
+    /// Added by the user rather than found on the asset source.
+    #[serde(skip)]
+    pub custom: bool,
+}
+
+impl Character {
+    pub fn label(&self) -> String {
+        let mut label = if self.name.is_empty() {
+            self.code.clone()
+        }
+        else {
+            format!("{} ({})", self.code, self.name)
+        };
+        if self.custom {
+            label.push_str(" [custom]");
+        }
+        label
+    }
+}
+
+/// Where the current roster came from, to show next to the character list.
+#[derive(Copy, Clone, PartialEq)]
+pub enum RosterOrigin {
+    Manifest,
+    Directory,
+    BuiltIn,
+}
+
+impl RosterOrigin {
+    pub fn description(self) -> &'static str {
+        match self {
+            RosterOrigin::Manifest => "from roster.json",
+            RosterOrigin::Directory => "from the COL_*.pac files in pacs/",
+            RosterOrigin::BuiltIn => "built in",
+        }
+    }
+}
+
+/// Whether `code` can be used in file names and paths: letters, digits and underscores only, so
+/// a code such as `../x` can't reach outside the asset source.
+pub fn is_valid_code(code: &str) -> bool {
+    !code.is_empty() && code.chars().all(|c| c.is_ascii_alphanumeric() || c == '_')
+}
+
+pub fn built_in(game: Game) -> Vec<Character> {
+    game.roster()
+        .iter()
+        .map(|(code, name)| Character { code: code.to_string(), name: name.to_string(), custom: false })
+        .collect()
+}
+
+/// Discovers the roster on `source`: a local directory is scanned for `COL_*.pac` files,
+/// a web source is asked for its manifest.
+pub fn load(source: &AssetSource, game: Game, on_done: impl 'static + Send + FnOnce(Result<(Vec<Character>, RosterOrigin), LoadError>)) {
+    match source {
+        AssetSource::Directory(directory) => {
+            let pacs = std::path::Path::new(directory).join(game.asset_path("pacs"));
+            on_done(scan_directory(&pacs).map(|codes| (codes, RosterOrigin::Directory)));
+        }
+        AssetSource::Url(_) => {
+            source.fetch(&game.asset_path(MANIFEST), move |response| {
+                on_done(response.and_then(|bytes| parse_manifest(&bytes)).map(|characters| (characters, RosterOrigin::Manifest)));
+            });
+        }
+    }
+}
+
+fn parse_manifest(bytes: &[u8]) -> Result<Vec<Character>, LoadError> {
+    serde_json::from_slice(bytes).map_err(|e| LoadError::Decode(format!("{} is not a valid roster: {}", MANIFEST, e)))
+}
+
+fn scan_directory(pacs: &std::path::Path) -> Result<Vec<Character>, LoadError> {
+    let entries = std::fs::read_dir(pacs).map_err(|e| LoadError::Io(format!("Could not list {}: {}", pacs.display(), e)))?;
+    let codes: BTreeSet<String> = entries
+        .filter_map(|entry| entry.ok())
+        .filter_map(|entry| {
+            let file_name = entry.file_name().to_string_lossy().into_owned();
+            let code = file_name.strip_prefix("COL_")?.strip_suffix(".pac")?;
+            Some(code.to_string())
+        })
+        .collect();
+    Ok(codes.into_iter().map(|code| Character { code, name: String::new(), custom: false }).collect())
+}
+
+/// Combines a discovered roster with the user's own codes. Characters without a name get
+/// the built-in one if the code is known. Codes that aren't `is_valid_code` are left out.
+pub fn merge(game: Game, discovered: Vec<Character>, custom: &[String]) -> Vec<Character> {
+    let known = built_in(game);
+    let mut roster: Vec<Character> = discovered
+        .into_iter()
+        .filter(|character| is_valid_code(&character.code))
+        .map(|mut character| {
+            if character.name.is_empty() {
+                if let Some(known) = known.iter().find(|known| known.code == character.code) {
+                    character.name = known.name.clone();
+                }
+            }
+            character
+        })
+        .collect();
+    for code in custom.iter().filter(|code| is_valid_code(code)) {
+        if !roster.iter().any(|character| &character.code == code) {
+            roster.push(Character { code: code.clone(), name: String::new(), custom: true });
+        }
+    }
+    roster
+}
@@ -0,0 +1,57 @@
+/// Asks the user where to put `file_name` and writes `bytes` there.
+/// Returns `Ok(false)` if the dialog was cancelled.
+#[cfg(not(target_arch = "wasm32"))]
+pub fn save_file(file_name: &str, bytes: &[u8]) -> Result<bool, String> {
+    let mut dialog = rfd::FileDialog::new().set_file_name(file_name);
+    if let Some(extension) = file_name.rsplit_once('.').map(|(_, extension)| extension) {
+        dialog = dialog.add_filter(extension, &[extension]);
+    }
+    match dialog.save_file() {
+        Some(path) => match std::fs::write(&path, bytes) {
+            Ok(()) => Ok(true),
+            Err(e) => Err(format!("Could not write {}: {}", path.display(), e)),
+        },
+        None => Ok(false),
+    }
+}
+
+/// How long a download link stays valid after it has been clicked.
+#[cfg(target_arch = "wasm32")]
+const REVOKE_DELAY_MS: i32 = 60_000;
+
+/// Hands `bytes` to the browser as a download named `file_name`.
+#[cfg(target_arch = "wasm32")]
+pub fn save_file(file_name: &str, bytes: &[u8]) -> Result<bool, String> {
+    use wasm_bindgen::{closure::Closure, JsCast, JsValue};
+
+    let js_error = |e: JsValue| format!("{:?}", e);
+    let window = web_sys::window().ok_or("No window available")?;
+    let document = window.document().ok_or("No document available")?;
+
+    let parts = js_sys::Array::new();
+    parts.push(&js_sys::Uint8Array::from(bytes));
+    let blob = web_sys::Blob::new_with_u8_array_sequence_and_options(
+        &parts,
+        web_sys::BlobPropertyBag::new().type_("application/octet-stream"),
+    )
+    .map_err(js_error)?;
+    let url = web_sys::Url::create_object_url_with_blob(&blob).map_err(js_error)?;
+
+    let anchor: web_sys::HtmlAnchorElement = document
+        .create_element("a")
+        .map_err(js_error)?
+        .dyn_into()
+        .map_err(|_| "Could not create a download link")?;
+    anchor.set_href(&url);
+    anchor.set_download(file_name);
+    anchor.click();
+    // Revoking the URL straight away cancels the download in some browsers (Firefox in
+    // particular), so give the download time to start first.
+    let revoke = Closure::once_into_js(move || {
+        let _ = web_sys::Url::revoke_object_url(&url);
+    });
+    window
+        .set_timeout_with_callback_and_timeout_and_arguments_0(revoke.unchecked_ref(), REVOKE_DELAY_MS)
+        .map_err(js_error)?;
+    Ok(true)
+}
@@ -0,0 +1,88 @@
+use eframe::egui;
+use serde::{Deserialize, Serialize};
+use super::error::LoadError;
+
+pub const DEFAULT_BASE_URL: &str = "https://wistfulhopes.neocities.org";
+
+/// Where the collision PACs, scripts, command DBs and sprite images are loaded from.
+/// Both kinds of source use the same layout: `pacs/`, `scripts/`, `rons/` and `images/<CHAR>/`.
+#[derive(Clone, PartialEq, Serialize, Deserialize)]
+pub enum AssetSource {
+    /// A web server or mirror, given by its base URL.
+    Url(String),
+    /// A local checkout of the extracted game files. Not available on the web.
+    Directory(String),
+}
+
+impl Default for AssetSource {
+    fn default() -> Self {
+        AssetSource::Url(DEFAULT_BASE_URL.to_string())
+    }
+}
+
+impl AssetSource {
+    /// Loads `path`, relative to the root of the source, and hands the bytes to `on_done`.
+    /// Downloads complete in the background; local files are read straight away.
+    pub fn fetch(&self, path: &str, on_done: impl 'static + Send + FnOnce(Result<Vec<u8>, LoadError>)) {
+        match self {
+            AssetSource::Url(base_url) => {
+                let request = ehttp::Request::get(format!("{}/{}", base_url.trim_end_matches('/'), path));
+                ehttp::fetch(request, move |response| {
+                    on_done(match response {
+                        Ok(response) if response.ok => Ok(response.bytes),
+                        Ok(response) => Err(LoadError::Http {
+                            url: response.url,
+                            status: response.status,
+                            status_text: response.status_text,
+                        }),
+                        Err(e) => Err(LoadError::Network(e)),
+                    });
+                });
+            }
+            AssetSource::Directory(directory) => {
+                let path = std::path::Path::new(directory).join(path);
+                on_done(std::fs::read(&path).map_err(|e| LoadError::Io(format!("Could not read {}: {}", path.display(), e))));
+            }
+        }
+    }
+
+    /// Editor for the source settings. Returns true when the user asked to reload from the new source.
+    pub fn ui(&mut self, ui: &mut egui::Ui) -> bool {
+        ui.horizontal(|ui| {
+            if ui.radio(matches!(self, AssetSource::Url(_)), "Web server").clicked() && !matches!(self, AssetSource::Url(_)) {
+                *self = AssetSource::default();
+            }
+            if cfg!(not(target_arch = "wasm32"))
+                && ui.radio(matches!(self, AssetSource::Directory(_)), "Local directory").clicked()
+                && !matches!(self, AssetSource::Directory(_))
+            {
+                *self = AssetSource::Directory(String::new());
+            }
+        });
+        match self {
+            AssetSource::Url(base_url) => {
+                ui.horizontal(|ui| {
+                    ui.label("Base URL");
+                    ui.text_edit_singleline(base_url);
+                    if ui.button("Reset").clicked() {
+                        *base_url = DEFAULT_BASE_URL.to_string();
+                    }
+                });
+            }
+            AssetSource::Directory(directory) => {
+                ui.horizontal(|ui| {
+                    ui.label("Directory");
+                    ui.text_edit_singleline(directory);
+                    #[cfg(not(target_arch = "wasm32"))]
+                    if ui.button("Browse…").clicked() {
+                        if let Some(path) = rfd::FileDialog::new().pick_folder() {
+                            *directory = path.display().to_string();
+                        }
+                    }
+                });
+            }
+        }
+        ui.label("The source should contain pacs/, scripts/, rons/ and images/<CHAR>/.");
+        ui.button("Apply and reload").clicked()
+    }
+}
@@ -0,0 +1,298 @@
+/// An argument of a script instruction, as printed by bbscript.
+#[derive(Clone, PartialEq, Debug)]
+pub enum Arg {
+    /// A quoted string such as a sprite or state name, with its quotes (and any type prefix) removed.
+    Str(String),
+    Int(i64),
+    /// Anything else, e.g. a named constant, kept as written.
+    Other(String),
+}
+
+impl Arg {
+    fn parse(text: &str) -> Arg {
+        let text = text.trim();
+        if let Some(quote) = text.find('\'') {
+            // Strings may carry a type prefix such as `s32'name'`.
+            if text.ends_with('\'') && quote < text.len() - 1 && text[..quote].chars().all(|c| c.is_ascii_alphanumeric()) {
+                return Arg::Str(text[quote + 1..text.len() - 1].to_string());
+            }
+        }
+        match text.parse() {
+            Ok(value) => Arg::Int(value),
+            Err(_) => Arg::Other(text.to_string()),
+        }
+    }
+
+    pub fn text(&self) -> String {
+        match self {
+            Arg::Str(value) | Arg::Other(value) => value.clone(),
+            Arg::Int(value) => value.to_string(),
+        }
+    }
+
+    pub fn as_str(&self) -> Option<&str> {
+        match self {
+            Arg::Str(value) => Some(value),
+            _ => None,
+        }
+    }
+
+    pub fn as_int(&self) -> Option<i64> {
+        match self {
+            Arg::Int(value) => Some(*value),
+            _ => None,
+        }
+    }
+}
+
+/// One line of a state.
+#[derive(Clone, PartialEq, Debug)]
+pub struct Instruction {
+    pub name: String,
+    pub args: Vec<Arg>,
+    /// Leading whitespace of the line, which bbscript uses to show nesting.
+    pub indent: usize,
+    /// The line as written, without its indentation.
+    pub text: String,
+}
+
+impl Instruction {
+    /// Splits `name: arg, arg` into its parts. Lines without a `name:` prefix, such as `endUpon`,
+    /// are instructions without arguments.
+    fn parse(line: &str) -> Instruction {
+        let text = line.trim();
+        let indent = line.len() - line.trim_start().len();
+        let (name, args) = match text.split_once(':') {
+            Some((name, args)) if !name.is_empty() && name.chars().all(|c| c.is_ascii_alphanumeric() || c == '_') => {
+                (name.to_string(), split_args(args).into_iter().map(Arg::parse).collect())
+            }
+            _ => (text.to_string(), vec![]),
+        };
+        Instruction { name, args, indent, text: text.to_string() }
+    }
+
+    pub fn arg_str(&self, index: usize) -> Option<&str> {
+        self.args.get(index).and_then(Arg::as_str)
+    }
+
+    pub fn arg_int(&self, index: usize) -> Option<i64> {
+        self.args.get(index).and_then(Arg::as_int)
+    }
+}
+
+/// Splits an argument list at commas that aren't inside quotes.
+fn split_args(args: &str) -> Vec<&str> {
+    let mut parts = vec![];
+    let mut in_quotes = false;
+    let mut start = 0;
+    for (index, c) in args.char_indices() {
+        match c {
+            '\'' => in_quotes = !in_quotes,
+            ',' if !in_quotes => {
+                parts.push(&args[start..index]);
+                start = index + 1;
+            }
+            _ => (),
+        }
+    }
+    if !args[start..].trim().is_empty() || !parts.is_empty() {
+        parts.push(&args[start..]);
+    }
+    parts
+}
+
+/// A sprite shown by a state, and for how many frames.
+#[derive(Clone, PartialEq, Debug)]
+pub struct SpriteCall {
+    pub name: String,
+    pub duration: u32,
+    /// Index of the `sprite` instruction in `State::instructions`.
+    pub instruction: usize,
+}
+
+#[derive(Clone, PartialEq, Debug, Default)]
+pub struct State {
+    pub name: String,
+    /// The instructions between `beginState` and `endState`, in order. Blocks such as `upon` or
+    /// `if` aren't nested into children: everything that reads a state (the sprite timeline, frame
+    /// data and the state panel) walks it in script order and refers to instructions by index,
+    /// and `indent` already carries the nesting bbscript printed for display.
+    pub instructions: Vec<Instruction>,
+}
+
+impl State {
+    pub fn sprites(&self) -> Vec<SpriteCall> {
+        self.instructions
+            .iter()
+            .enumerate()
+            .filter(|(_, instruction)| instruction.name == "sprite")
+            .filter_map(|(index, instruction)| {
+                Some(SpriteCall {
+                    name: instruction.arg_str(0)?.to_string(),
+                    duration: instruction.arg_int(1).unwrap_or(0).max(0) as u32,
+                    instruction: index,
+                })
+            })
+            .collect()
+    }
+
+    /// Number of frames the state's sprites take up, end to end.
+    pub fn total_frames(&self) -> u32 {
+        self.sprites().iter().map(|sprite| sprite.duration).sum()
+    }
+
+    /// The sprite shown on `frame`, counting from 0.
+    pub fn sprite_at(&self, frame: u32) -> Option<SpriteCall> {
+        let mut start = 0;
+        for sprite in self.sprites() {
+            if frame < start + sprite.duration {
+                return Some(sprite);
+            }
+            start += sprite.duration;
+        }
+        None
+    }
+
+    /// The first frame of the sprite set by the instruction at `instruction`.
+    pub fn start_frame(&self, instruction: usize) -> Option<u32> {
+        let mut start = 0;
+        for sprite in self.sprites() {
+            if sprite.instruction == instruction {
+                return Some(start);
+            }
+            start += sprite.duration;
+        }
+        None
+    }
+}
+
+/// The states of a script, plus anything about its structure that didn't add up.
+#[derive(Default)]
+pub struct Script {
+    pub states: Vec<State>,
+    pub errors: Vec<String>,
+}
+
+/// Reads the states out of bbscript's text output. Only whole `beginState`/`endState`
+/// instructions delimit states, so labels or strings that merely contain those words don't,
+/// and a missing `endState` is reported rather than pairing up the wrong states.
+pub fn parse(script: &str) -> Script {
+    let mut result = Script::default();
+    let mut current: Option<State> = None;
+    for (line_number, line) in script.lines().enumerate() {
+        let trimmed = line.trim();
+        if trimmed.is_empty() || trimmed.starts_with('#') || trimmed.starts_with("//") {
+            continue;
+        }
+        let instruction = Instruction::parse(line);
+        match instruction.name.as_str() {
+            "beginState" => {
+                if let Some(state) = current.take() {
+                    result.errors.push(format!("Line {}: state {} has no endState", line_number + 1, state.name));
+                    result.states.push(state);
+                }
+                let name = instruction.args.first().map(Arg::text).unwrap_or_default();
+                current = Some(State { name, instructions: vec![] });
+            }
+            "endState" => match current.take() {
+                Some(state) => result.states.push(state),
+                None => result.errors.push(format!("Line {}: endState outside of a state", line_number + 1)),
+            },
+            _ => {
+                // Instructions outside of states, such as the script header, aren't of interest.
+                if let Some(state) = &mut current {
+                    state.instructions.push(instruction);
+                }
+            }
+        }
+    }
+    if let Some(state) = current.take() {
+        result.errors.push(format!("Line {}: state {} has no endState", script.lines().count(), state.name));
+        result.states.push(state);
+    }
+    result
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn arg_strips_quotes_and_type_prefixes() {
+        assert_eq!(Arg::parse("s32'sol000_00'"), Arg::Str("sol000_00".to_string()));
+        assert_eq!(Arg::parse(" 'plain' "), Arg::Str("plain".to_string()));
+        assert_eq!(Arg::parse("-12"), Arg::Int(-12));
+        assert_eq!(Arg::parse("SOME_CONSTANT"), Arg::Other("SOME_CONSTANT".to_string()));
+        // A quote that isn't preceded by a plain type prefix isn't a string.
+        assert_eq!(Arg::parse("a-b'c'"), Arg::Other("a-b'c'".to_string()));
+    }
+
+    #[test]
+    fn split_args_ignores_quoted_commas() {
+        assert_eq!(split_args(" s32'a,b', 3"), vec![" s32'a,b'", " 3"]);
+        assert_eq!(split_args(""), Vec::<&str>::new());
+        assert_eq!(split_args("1,,2"), vec!["1", "", "2"]);
+    }
+
+    #[test]
+    fn instruction_parses_name_args_and_indent() {
+        let instruction = Instruction::parse("    sprite: s32'sol000_00', 3");
+        assert_eq!(instruction.name, "sprite");
+        assert_eq!(instruction.indent, 4);
+        assert_eq!(instruction.arg_str(0), Some("sol000_00"));
+        assert_eq!(instruction.arg_int(1), Some(3));
+        assert_eq!(instruction.text, "sprite: s32'sol000_00', 3");
+
+        let instruction = Instruction::parse("endUpon");
+        assert_eq!(instruction.name, "endUpon");
+        assert!(instruction.args.is_empty());
+
+        // Only an identifier before the colon names the instruction.
+        let instruction = Instruction::parse("'not: a name'");
+        assert_eq!(instruction.name, "'not: a name'");
+        assert!(instruction.args.is_empty());
+    }
+
+    #[test]
+    fn parse_only_splits_states_on_whole_instructions() {
+        let script = "\
+beginState: s32'NmlAtk5A'
+    label: s32'beginState_loop'
+    # beginState: s32'Commented'
+    // endState
+    sprite: s32'sol000_00', 3
+    sprite: s32'sol000_01, alt', 2
+endState
+";
+        let script = parse(script);
+        assert!(script.errors.is_empty(), "{:?}", script.errors);
+        assert_eq!(script.states.len(), 1);
+        let state = &script.states[0];
+        assert_eq!(state.name, "NmlAtk5A");
+        assert_eq!(state.instructions.len(), 3);
+        assert_eq!(state.instructions[0].arg_str(0), Some("beginState_loop"));
+        let sprites = state.sprites();
+        assert_eq!(sprites.len(), 2);
+        assert_eq!(sprites[1].name, "sol000_01, alt");
+        assert_eq!(sprites[1].duration, 2);
+        assert_eq!(state.total_frames(), 5);
+        assert_eq!(state.sprite_at(3).map(|sprite| sprite.name), Some("sol000_01, alt".to_string()));
+        assert_eq!(state.start_frame(sprites[1].instruction), Some(3));
+    }
+
+    #[test]
+    fn parse_reports_missing_end_state() {
+        let script = parse("beginState: s32'First'\nsprite: s32'a', 1\nbeginState: s32'Second'\nsprite: s32'b', 1\n");
+        assert_eq!(script.states.iter().map(|state| state.name.as_str()).collect::<Vec<_>>(), vec!["First", "Second"]);
+        assert_eq!(
+            script.errors,
+            vec![
+                "Line 3: state First has no endState".to_string(),
+                "Line 4: state Second has no endState".to_string(),
+            ]
+        );
+
+        let script = parse("endState\n");
+        assert_eq!(script.errors, vec!["Line 1: endState outside of a state".to_string()]);
+    }
+}
@@ -0,0 +1,90 @@
+//! Checks that the editor writes unedited collision data back byte for byte, so a saved PAC
+//! only differs from the game's where boxes were actually changed.
+
+use std::fmt;
+use super::game::Game;
+
+/// Where a re-serialized file first stops matching the original.
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub struct Difference {
+    pub offset: usize,
+    pub original_len: usize,
+    pub rewritten_len: usize,
+}
+
+impl fmt::Display for Difference {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "differs from byte 0x{:x} (original {} bytes, rewritten {} bytes)",
+            self.offset, self.original_len, self.rewritten_len
+        )
+    }
+}
+
+/// The first byte at which `original` and `rewritten` differ, if they do.
+pub fn first_difference(original: &[u8], rewritten: &[u8]) -> Option<Difference> {
+    let offset = match original.iter().zip(rewritten).position(|(a, b)| a != b) {
+        Some(offset) => offset,
+        None if original.len() == rewritten.len() => return None,
+        None => original.len().min(rewritten.len()),
+    };
+    Some(Difference {
+        offset,
+        original_len: original.len(),
+        rewritten_len: rewritten.len(),
+    })
+}
+
+pub struct FileReport {
+    pub name: String,
+    /// `Err` if the file couldn't be parsed as a jonbin, so it wasn't checked.
+    pub result: Result<Option<Difference>, String>,
+}
+
+pub struct Report {
+    /// How the whole archive compares after being written back.
+    pub pac: Option<Difference>,
+    pub files: Vec<FileReport>,
+}
+
+impl Report {
+    /// Whether the archive and every jonbin in it came back unchanged.
+    pub fn is_clean(&self) -> bool {
+        self.pac.is_none() && self.files.iter().all(|file| !matches!(file.result, Ok(Some(_))))
+    }
+
+    pub fn differing(&self) -> impl Iterator<Item = (&str, &Difference)> {
+        self.files.iter().filter_map(|file| match &file.result {
+            Ok(Some(difference)) => Some((file.name.as_str(), difference)),
+            _ => None,
+        })
+    }
+
+    pub fn unparsed(&self) -> impl Iterator<Item = (&str, &str)> {
+        self.files.iter().filter_map(|file| match &file.result {
+            Err(e) => Some((file.name.as_str(), e.as_str())),
+            _ => None,
+        })
+    }
+}
+
+/// Parses the PAC in `bytes` and every jonbin in it, writes them back without any edits and
+/// compares the results to the originals.
+pub fn verify(bytes: &[u8], game: Game) -> Result<Report, String> {
+    let pac = game.parse_pac(bytes)?;
+    let files = pac
+        .files
+        .iter()
+        .map(|file| FileReport {
+            name: file.name.clone(),
+            result: game
+                .parse_jonbin(&file.contents)
+                .map(|jonb| first_difference(&file.contents, &jonb.to_bytes())),
+        })
+        .collect();
+    Ok(Report {
+        pac: first_difference(bytes, &pac.to_bytes()),
+        files,
+    })
+}
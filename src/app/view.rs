@@ -0,0 +1,57 @@
+use eframe::emath::{Pos2, Rect, Vec2};
+
+/// The zoom levels offered in the canvas toolbar; wheel zoom is clamped to the same range.
+pub const ZOOM_PRESETS: [f32; 6] = [0.25, 0.5, 1.0, 2.0, 4.0, 8.0];
+const MIN_ZOOM: f32 = 0.25;
+const MAX_ZOOM: f32 = 8.0;
+
+/// Where the character's origin is in a sprite render, in image pixels.
+pub const IMAGE_ORIGIN: Vec2 = Vec2 { x: 960.0, y: 802.0 };
+
+/// Maps game units, relative to the character's origin, to screen points. Everything drawn on
+/// the canvas goes through the same transform so the render and the boxes stay aligned at
+/// every zoom level.
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub struct Transform {
+    /// Screen position of the character's origin.
+    pub origin: Pos2,
+    /// Screen pixels per game unit.
+    pub zoom: f32,
+}
+
+impl Default for Transform {
+    fn default() -> Self {
+        Self {
+            origin: Pos2 { x: 480.0, y: 802.0 },
+            zoom: 1.0,
+        }
+    }
+}
+
+impl Transform {
+    pub fn to_screen(&self, world: Pos2) -> Pos2 {
+        self.origin + world.to_vec2() * self.zoom
+    }
+
+    pub fn to_world(&self, screen: Pos2) -> Pos2 {
+        ((screen - self.origin) / self.zoom).to_pos2()
+    }
+
+    pub fn rect_to_screen(&self, world: Rect) -> Rect {
+        Rect::from_two_pos(self.to_screen(world.min), self.to_screen(world.max))
+    }
+
+    /// Changes the zoom while keeping whatever is under `anchor` in place.
+    pub fn zoom_around(&mut self, anchor: Pos2, zoom: f32) {
+        let world = self.to_world(anchor);
+        self.zoom = zoom.clamp(MIN_ZOOM, MAX_ZOOM);
+        self.origin = anchor - world.to_vec2() * self.zoom;
+    }
+
+    /// Zooms and pans so that `world` fills `screen`, leaving a small margin.
+    pub fn fit(&mut self, world: Rect, screen: Rect) {
+        let scale = (screen.width() / world.width().max(1.0)).min(screen.height() / world.height().max(1.0));
+        self.zoom = (scale * 0.9).clamp(MIN_ZOOM, MAX_ZOOM);
+        self.origin = screen.center() - world.center().to_vec2() * self.zoom;
+    }
+}
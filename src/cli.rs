@@ -0,0 +1,280 @@
+use std::{collections::BTreeMap, fs, path::{Path, PathBuf}};
+use anyhow::{anyhow, bail, Context, Result};
+use arcsys::ggst::{jonbin::GGSTJonBin, pac::GGSTPac};
+use bbscript::{command_db::GameDB, run_parser};
+use image::{Rgba, RgbaImage};
+use structopt::StructOpt;
+use crate::app::framedata::{self, TableFormat};
+use crate::app::game::{BoxShape, BoxType, Game};
+use crate::app::json::Document;
+use crate::app::open;
+use crate::app::states;
+use crate::app::verify;
+
+/// Without a subcommand the editor window opens; the subcommands run headless.
+#[derive(StructOpt)]
+#[structopt(name = "arcsys-collision-editor")]
+pub struct Opt {
+    #[structopt(subcommand)]
+    pub command: Option<Command>,
+}
+
+#[derive(StructOpt)]
+pub enum Command {
+    /// Writes the boxes of every jonbin in a PAC out as JSON
+    Dump {
+        #[structopt(parse(from_os_str))]
+        pac: PathBuf,
+        /// Where to write the JSON, instead of stdout
+        #[structopt(short, long, parse(from_os_str))]
+        output: Option<PathBuf>,
+        #[structopt(long, default_value = "strive", parse(try_from_str = parse_game))]
+        game: Game,
+    },
+    /// Replaces the boxes of a PAC's jonbins with the ones in a JSON export
+    Pack {
+        #[structopt(parse(from_os_str))]
+        json: PathBuf,
+        /// The PAC to take everything but the boxes from
+        #[structopt(long, parse(from_os_str))]
+        base: PathBuf,
+        #[structopt(short, long, parse(from_os_str))]
+        output: PathBuf,
+        #[structopt(long, default_value = "strive", parse(try_from_str = parse_game))]
+        game: Game,
+    },
+    /// Writes a PAC and its jonbins back without edits and reports any byte that changed
+    Verify {
+        #[structopt(parse(from_os_str))]
+        pac: PathBuf,
+        #[structopt(long, default_value = "strive", parse(try_from_str = parse_game))]
+        game: Game,
+    },
+    /// Derives the frame data of every state in a character script
+    Framedata {
+        /// The character's compiled bbscript
+        #[structopt(parse(from_os_str))]
+        script: PathBuf,
+        /// The character's collision PAC
+        #[structopt(parse(from_os_str))]
+        pac: PathBuf,
+        /// The command database to decompile the script with
+        #[structopt(long, parse(from_os_str))]
+        ron: PathBuf,
+        /// csv or json
+        #[structopt(long, default_value = "csv", parse(try_from_str = parse_format))]
+        format: TableFormat,
+        /// Where to write the table, instead of stdout
+        #[structopt(short, long, parse(from_os_str))]
+        output: Option<PathBuf>,
+        #[structopt(long, default_value = "strive", parse(try_from_str = parse_game))]
+        game: Game,
+    },
+    /// Draws the boxes of a sprite as a PNG, on top of its render if one is given
+    Render {
+        #[structopt(parse(from_os_str))]
+        pac: PathBuf,
+        sprite: String,
+        /// A 1920x1080 render of the sprite, as shown behind the boxes in the editor
+        #[structopt(long, parse(from_os_str))]
+        image: Option<PathBuf>,
+        #[structopt(short, long, parse(from_os_str))]
+        output: PathBuf,
+        /// Where the character's origin is in the image
+        #[structopt(long, default_value = "960")]
+        origin_x: f32,
+        #[structopt(long, default_value = "802")]
+        origin_y: f32,
+        #[structopt(long, default_value = "strive", parse(try_from_str = parse_game))]
+        game: Game,
+    },
+}
+
+fn parse_game(name: &str) -> Result<Game> {
+    match name.to_lowercase().as_str() {
+        "strive" | "ggst" => Ok(Game::Strive),
+        "gbvs" => Ok(Game::Gbvs),
+        _ => bail!("unknown game {:?}, expected strive or gbvs", name),
+    }
+}
+
+fn parse_format(name: &str) -> Result<TableFormat> {
+    match name.to_lowercase().as_str() {
+        "csv" => Ok(TableFormat::Csv),
+        "json" => Ok(TableFormat::Json),
+        _ => bail!("unknown format {:?}, expected csv or json", name),
+    }
+}
+
+pub fn run(command: Command) -> Result<()> {
+    match command {
+        Command::Dump { pac, output, game } => {
+            let jonbins = read_jonbins(&read_pac(&pac, game)?, game);
+            let json = Document::pac(&jonbins).to_bytes().map_err(|e| anyhow!(e))?;
+            write_output(output.as_deref(), &json)
+        }
+        Command::Pack { json, base, output, game } => {
+            let document = Document::parse(&read(&json)?).map_err(|e| anyhow!("could not parse {}: {}", json.display(), e))?;
+            let mut pac = read_pac(&base, game)?;
+            let mut jonbins = read_jonbins(&pac, game);
+            for jonbin in document.jonbins() {
+                let jonb = jonbins
+                    .get_mut(&jonbin.name)
+                    .ok_or_else(|| anyhow!("{} is not in {}", jonbin.name, base.display()))?;
+                jonb.boxes = jonbin.apply(&jonb.boxes, game).map_err(|e| anyhow!("could not apply {}: {}", json.display(), e))?;
+                if let Some(file) = pac.files.iter_mut().find(|file| file.name == jonbin.name) {
+                    file.contents = jonb.to_bytes();
+                }
+            }
+            fs::write(&output, pac.to_bytes()).with_context(|| format!("could not write {}", output.display()))
+        }
+        Command::Verify { pac, game } => {
+            let report = verify::verify(&read(&pac)?, game)
+                .map_err(|e| anyhow!("could not parse {}: {}", pac.display(), e))?;
+            for (name, e) in report.unparsed() {
+                eprintln!("warning: {} was not checked: {}", name, e);
+            }
+            for (name, difference) in report.differing() {
+                println!("{} {}", name, difference);
+            }
+            if let Some(difference) = &report.pac {
+                println!("archive {}", difference);
+            }
+            if !report.is_clean() {
+                bail!("{} does not round-trip", pac.display());
+            }
+            println!("{} files checked, all unchanged", report.files.len());
+            Ok(())
+        }
+        Command::Framedata { script, pac, ron, format, output, game } => {
+            let db = GameDB::load(read(&ron)?).map_err(|e| anyhow!("could not load {}: {}", ron.display(), e))?;
+            let script_text = run_parser(&db, &read(&script)?, Some(0), Some(0), false)
+                .map_err(|e| anyhow!("could not parse {}: {}", script.display(), e))?;
+            let script = states::parse(&script_text);
+            for error in &script.errors {
+                eprintln!("warning: {}", error);
+            }
+            let jonbins = read_jonbins(&read_pac(&pac, game)?, game);
+            let rows = framedata::table(&script.states, &jonbins);
+            write_output(output.as_deref(), &format.write(&rows).map_err(|e| anyhow!(e))?)
+        }
+        Command::Render { pac, sprite, image, output, origin_x, origin_y, game } => {
+            let jonbins = read_jonbins(&read_pac(&pac, game)?, game);
+            let jonb = jonbins.get(&sprite).ok_or_else(|| anyhow!("{} has no sprite {}", pac.display(), sprite))?;
+            let mut canvas = match image {
+                Some(image) => image::open(&image).with_context(|| format!("could not open {}", image.display()))?.to_rgba8(),
+                None => RgbaImage::new(1920, 1080),
+            };
+            for hitbox in jonb.boxes.iter().flatten() {
+                let (color, shape) = match BoxType::try_from(hitbox.kind) {
+                    Ok(kind) => (game.box_color(kind), kind.shape()),
+                    Err(_) => (eframe::epaint::Color32::GREEN, BoxShape::Rect),
+                };
+                let color = Rgba([color.r(), color.g(), color.b(), 255]);
+                let x = origin_x + hitbox.rect.x_offset;
+                let y = origin_y + hitbox.rect.y_offset;
+                match shape {
+                    BoxShape::Rect => {
+                        let (left, right) = min_max(x, x + hitbox.rect.width);
+                        let (top, bottom) = min_max(y, y + hitbox.rect.height);
+                        draw_rect(&mut canvas, [left, top, right, bottom], color);
+                    }
+                    BoxShape::Point => draw_crosshair(&mut canvas, [x, y], color),
+                    BoxShape::Vector => draw_arrow(&mut canvas, [x, y], [x + hitbox.rect.width, y + hitbox.rect.height], color),
+                }
+            }
+            canvas.save(&output).with_context(|| format!("could not write {}", output.display()))
+        }
+    }
+}
+
+fn read(path: &Path) -> Result<Vec<u8>> {
+    fs::read(path).with_context(|| format!("could not read {}", path.display()))
+}
+
+fn read_pac(path: &Path, game: Game) -> Result<GGSTPac> {
+    open::open_file(read(path)?, game)
+        .map(|col| col.pac)
+        .map_err(|e| anyhow!("could not parse {}: {}", path.display(), e))
+}
+
+/// Parses every jonbin in `pac`, warning about the files that aren't one.
+fn read_jonbins(pac: &GGSTPac, game: Game) -> BTreeMap<String, GGSTJonBin> {
+    let mut jonbins = BTreeMap::new();
+    for file in &pac.files {
+        match game.parse_jonbin(&file.contents) {
+            Ok(jonb) => {
+                jonbins.insert(file.name.clone(), jonb);
+            }
+            Err(e) => eprintln!("warning: skipping {}: {}", file.name, e),
+        }
+    }
+    jonbins
+}
+
+fn write_output(path: Option<&Path>, bytes: &[u8]) -> Result<()> {
+    match path {
+        Some(path) => fs::write(path, bytes).with_context(|| format!("could not write {}", path.display())),
+        None => {
+            use std::io::Write;
+            std::io::stdout().write_all(bytes)?;
+            Ok(())
+        }
+    }
+}
+
+fn min_max(a: f32, b: f32) -> (f32, f32) {
+    if a < b { (a, b) } else { (b, a) }
+}
+
+/// Outlines `[left, top, right, bottom]` three pixels thick, like the editor does, clipping
+/// whatever falls outside the canvas.
+fn draw_rect(canvas: &mut RgbaImage, [left, top, right, bottom]: [f32; 4], color: Rgba<u8>) {
+    const THICKNESS: i64 = 3;
+    let (width, height) = (canvas.width() as i64, canvas.height() as i64);
+    let (left, top, right, bottom) = (left.round() as i64, top.round() as i64, right.round() as i64, bottom.round() as i64);
+    for y in top - 1..=bottom + 1 {
+        for x in left - 1..=right + 1 {
+            let on_edge = (x - left).abs() <= THICKNESS / 2
+                || (x - right).abs() <= THICKNESS / 2
+                || (y - top).abs() <= THICKNESS / 2
+                || (y - bottom).abs() <= THICKNESS / 2;
+            if on_edge && (0..width).contains(&x) && (0..height).contains(&y) {
+                canvas.put_pixel(x as u32, y as u32, color);
+            }
+        }
+    }
+}
+
+/// Draws a line two pixels thick from `from` to `to`, clipping whatever falls outside the canvas.
+fn draw_line(canvas: &mut RgbaImage, [x0, y0]: [f32; 2], [x1, y1]: [f32; 2], color: Rgba<u8>) {
+    let (width, height) = (canvas.width() as i64, canvas.height() as i64);
+    let steps = (x1 - x0).abs().max((y1 - y0).abs()).ceil().max(1.0);
+    for step in 0..=steps as i64 {
+        let t = step as f32 / steps;
+        let (x, y) = ((x0 + (x1 - x0) * t).round() as i64, (y0 + (y1 - y0) * t).round() as i64);
+        for (x, y) in [(x, y), (x + 1, y), (x, y + 1), (x + 1, y + 1)] {
+            if (0..width).contains(&x) && (0..height).contains(&y) {
+                canvas.put_pixel(x as u32, y as u32, color);
+            }
+        }
+    }
+}
+
+/// Marks a point with a cross, the same size as in the editor.
+fn draw_crosshair(canvas: &mut RgbaImage, [x, y]: [f32; 2], color: Rgba<u8>) {
+    const SIZE: f32 = 8.0;
+    draw_line(canvas, [x - SIZE, y], [x + SIZE, y], color);
+    draw_line(canvas, [x, y - SIZE], [x, y + SIZE], color);
+}
+
+/// Draws an arrow from `start` to `tip` with a head a quarter of its length, like egui's
+/// `Painter::arrow` that the editor uses.
+fn draw_arrow(canvas: &mut RgbaImage, start: [f32; 2], tip: [f32; 2], color: Rgba<u8>) {
+    draw_line(canvas, start, tip, color);
+    let (dx, dy) = ((start[0] - tip[0]) / 4.0, (start[1] - tip[1]) / 4.0);
+    let (sin, cos) = std::f32::consts::FRAC_PI_6.sin_cos();
+    for sin in [sin, -sin] {
+        draw_line(canvas, tip, [tip[0] + dx * cos - dy * sin, tip[1] + dx * sin + dy * cos], color);
+    }
+}
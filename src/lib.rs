@@ -1,5 +1,7 @@
 mod app;
 pub use app::MyApp;
+pub use app::game::{BoxType, Game};
+pub use app::verify;
 
 // ----------------------------------------------------------------------------
 // When compiling for web:
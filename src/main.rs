@@ -1,8 +1,18 @@
 mod app;
+mod cli;
 pub use app::MyApp;
 use eframe::emath::Vec2;
+use structopt::StructOpt;
 
 fn main() {
+    if let Some(command) = cli::Opt::from_args().command {
+        if let Err(e) = cli::run(command) {
+            eprintln!("error: {:#}", e);
+            std::process::exit(1);
+        }
+        return;
+    }
+
     let options = eframe::NativeOptions {
         drag_and_drop_support: true,
         initial_window_size: Some(Vec2{x: 1280.0, y: 720.0}),
@@ -11,6 +21,6 @@ fn main() {
     eframe::run_native(
         "GGST Collision Editor Rust v3.4",
         options,
-        Box::new(|_cc| Box::new(MyApp::default())),
+        Box::new(|cc| Box::new(MyApp::new(cc))),
     )
-}
\ No newline at end of file
+}
@@ -0,0 +1,99 @@
+//! Round-trip checks of PAC and jonbin serialization over the files in `tests/fixtures`.
+
+#![cfg(not(target_arch = "wasm32"))]
+
+use std::{fs, path::Path};
+use arcsys_collision_editor_wasm::verify::{first_difference, verify, Difference};
+use arcsys_collision_editor_wasm::{BoxType, Game};
+
+/// Every PAC in `tests/fixtures/<game>`.
+fn fixtures(game: &str) -> Vec<(String, Vec<u8>)> {
+    let dir = Path::new(env!("CARGO_MANIFEST_DIR")).join("tests/fixtures").join(game);
+    let mut fixtures: Vec<_> = fs::read_dir(&dir)
+        .into_iter()
+        .flatten()
+        .filter_map(|entry| entry.ok())
+        .map(|entry| entry.path())
+        .filter(|path| path.extension().map_or(false, |extension| extension.eq_ignore_ascii_case("pac")))
+        .map(|path| (path.display().to_string(), fs::read(&path).unwrap()))
+        .collect();
+    fixtures.sort();
+    fixtures
+}
+
+/// Checks every fixture of `game` and returns how many there were.
+fn check_fixtures(directory: &str, game: Game) -> usize {
+    let fixtures = fixtures(directory);
+    for (path, bytes) in &fixtures {
+        let report = verify(bytes, game).unwrap_or_else(|e| panic!("{}: {}", path, e));
+        for (name, difference) in report.differing() {
+            eprintln!("{}: {} {}", path, name, difference);
+        }
+        if let Some(difference) = &report.pac {
+            eprintln!("{}: archive {}", path, difference);
+        }
+        let unparsed: Vec<_> = report.unparsed().collect();
+        assert!(unparsed.is_empty(), "{} has files that aren't jonbins: {:?}", path, unparsed);
+        assert!(report.is_clean(), "{} does not round-trip", path);
+    }
+    fixtures.len()
+}
+
+#[test]
+fn fixtures_round_trip() {
+    let checked = check_fixtures("strive", Game::Strive) + check_fixtures("gbvs", Game::Gbvs);
+    assert!(checked > 0, "no fixtures found, see tests/fixtures/README.md");
+}
+
+#[test]
+fn corrupted_fixture_is_reported() {
+    let fixtures = fixtures("strive");
+    assert!(!fixtures.is_empty(), "no Strive fixtures found, see tests/fixtures/README.md");
+    for (path, mut bytes) in fixtures {
+        // Cut off the last byte: the archive may still parse, but it can no longer be written
+        // back to the same bytes.
+        bytes.truncate(bytes.len() - 1);
+        assert!(
+            verify(&bytes, Game::Strive).map_or(true, |report| !report.is_clean()),
+            "{} with its last byte cut off still round-trips",
+            path
+        );
+    }
+}
+
+#[test]
+fn checked_in_fixtures_have_boxes() {
+    // The round trip only means something for box rects if the fixtures actually have some.
+    for (directory, game) in [("strive", Game::Strive), ("gbvs", Game::Gbvs)] {
+        let path = Path::new(env!("CARGO_MANIFEST_DIR")).join("tests/fixtures").join(directory).join("COL_fixture.pac");
+        let bytes = fs::read(&path).unwrap_or_else(|e| panic!("{}: {}", path.display(), e));
+        let pac = game.parse_pac(&bytes).unwrap_or_else(|e| panic!("{}: {}", path.display(), e));
+        for kind in [BoxType::Hurtbox, BoxType::Hitbox, BoxType::ExVector] {
+            let count: usize = pac
+                .files
+                .iter()
+                .filter_map(|file| game.parse_jonbin(&file.contents).ok())
+                .filter_map(|jonb| jonb.boxes.get(kind as usize).map(Vec::len))
+                .sum();
+            assert!(count > 0, "{} has no {} boxes", path.display(), kind.name());
+        }
+    }
+}
+
+#[test]
+fn not_a_pac_is_an_error() {
+    assert!(verify(b"not a pac", Game::Strive).is_err());
+}
+
+#[test]
+fn first_difference_finds_changed_and_missing_bytes() {
+    assert_eq!(first_difference(b"FPAC", b"FPAC"), None);
+    assert_eq!(
+        first_difference(b"FPAC", b"FPXC"),
+        Some(Difference { offset: 2, original_len: 4, rewritten_len: 4 })
+    );
+    assert_eq!(
+        first_difference(b"FPAC", b"FPACXX"),
+        Some(Difference { offset: 4, original_len: 4, rewritten_len: 6 })
+    );
+}